@@ -0,0 +1,232 @@
+use crate::search::bfs;
+use crate::vm::{Instruction, Program};
+use anyhow::{anyhow, bail, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+// a line of source: an optional `label:`, then an optional instruction, then an optional
+// `; comment`
+struct Line<'a> {
+    number: usize,
+    label: Option<&'a str>,
+    instruction: Option<&'a str>,
+}
+
+fn is_label(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn split_line(number: usize, line: &str) -> Result<Line<'_>> {
+    let code = line
+        .split(';')
+        .next()
+        .expect("split always yields a part")
+        .trim();
+    let (label, instruction) = match code.find(':') {
+        Some(colon) => {
+            let label = code[..colon].trim();
+            if !is_label(label) {
+                bail!("line {}: invalid label `{}`", number, label);
+            }
+            (Some(label), code[colon + 1..].trim())
+        }
+        None => (None, code),
+    };
+    Ok(Line {
+        number,
+        label,
+        instruction: Some(instruction).filter(|i| !i.is_empty()),
+    })
+}
+
+// parses a program whose jmp and nop arguments can be labels as well as offsets. a label
+// names the instruction after it, or the end of the program if there isn't one. blank lines
+// and `;` comments are skipped, so plain puzzle input assembles as it is
+pub fn assemble(source: &str) -> Result<Program<Instruction>> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| split_line(i + 1, line))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut labels = HashMap::new();
+    let mut pc = 0;
+    for line in &lines {
+        if let Some(label) = line.label {
+            if labels.insert(label, pc).is_some() {
+                bail!("line {}: label `{}` defined twice", line.number, label);
+            }
+        }
+        if line.instruction.is_some() {
+            pc += 1;
+        }
+    }
+
+    let mut instructions = vec![];
+    for line in &lines {
+        let code = match line.instruction {
+            Some(code) => code,
+            None => continue,
+        };
+        let pc = instructions.len() as i64;
+        let instruction = match code.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [op @ ("jmp" | "nop"), label] if is_label(label) => {
+                let target = labels
+                    .get(label)
+                    .ok_or_else(|| anyhow!("line {}: unknown label `{}`", line.number, label))?;
+                let offset = *target as i64 - pc;
+                match *op {
+                    "jmp" => Instruction::Jmp(offset),
+                    _ => Instruction::Nop(offset),
+                }
+            }
+            _ => Instruction::try_from(code).map_err(|e| anyhow!("line {}: {}", line.number, e))?,
+        };
+        instructions.push(instruction);
+    }
+    Ok(Program::from(instructions))
+}
+
+// the instructions never run, following the program from the start. the console has no
+// conditional jumps, so only the one path counts
+pub fn dead_code(program: &Program<Instruction>) -> Vec<usize> {
+    if program.is_empty() {
+        return vec![];
+    }
+    let live = bfs(0, |&pc| {
+        let next = program[pc].target(pc as i64);
+        Some(next)
+            .filter(|&next| next >= 0 && next < program.len() as i64)
+            .map(|next| next as usize)
+    });
+    (0..program.len()).filter(|pc| !live.contains(pc)).collect()
+}
+
+// the program as source with labels in place of offsets, `L<pc>` for instructions and `end`
+// for just past the last one. offsets leaving the program are kept as they are, and dead code
+// is marked with a comment. assembles back to the same program
+pub fn normalise(program: &Program<Instruction>) -> String {
+    let len = program.len() as i64;
+    let target = |pc: usize| match program[pc] {
+        Instruction::Jmp(n) | Instruction::Nop(n) => {
+            Some(pc as i64 + n).filter(|t| (0..=len).contains(t))
+        }
+        Instruction::Acc(_) => None,
+    };
+    let name = |target: i64| match target {
+        t if t == len => "end".to_string(),
+        t => format!("L{}", t),
+    };
+    let targets = (0..program.len())
+        .filter_map(target)
+        .collect::<BTreeSet<_>>();
+    let dead = dead_code(program);
+
+    let mut out = String::new();
+    for (pc, instruction) in program.iter().enumerate() {
+        if targets.contains(&(pc as i64)) {
+            writeln!(out, "{}:", name(pc as i64)).expect("writing to a string");
+        }
+        let code = match (instruction, target(pc)) {
+            (Instruction::Jmp(_), Some(t)) => format!("jmp {}", name(t)),
+            (Instruction::Nop(_), Some(t)) => format!("nop {}", name(t)),
+            _ => instruction.to_string(),
+        };
+        if dead.contains(&pc) {
+            writeln!(out, "    {:<12}; unreachable", code).expect("writing to a string");
+        } else {
+            writeln!(out, "    {}", code).expect("writing to a string");
+        }
+    }
+    if targets.contains(&len) {
+        writeln!(out, "end:").expect("writing to a string");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+    #[test]
+    fn test_assemble() -> Result<()> {
+        assert_eq!(assemble(INPUT)?, INPUT.parse()?);
+        let program = assemble(
+            "; adds two, then stops
+    acc +1
+loop:
+    acc +1          ; again
+    jmp done
+    jmp loop
+done: nop end
+end:
+",
+        )?;
+        assert_eq!(
+            program.to_string(),
+            "acc +1\nacc +1\njmp +2\njmp -2\nnop +1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let err = |source| assemble(source).unwrap_err().to_string();
+        assert_eq!(err("jmp nowhere"), "line 1: unknown label `nowhere`");
+        assert_eq!(err("a:\na: nop +0"), "line 2: label `a` defined twice");
+        assert_eq!(err("1a: nop +0"), "line 1: invalid label `1a`");
+        assert_eq!(err("acc +1\nacc x"), "line 2: invalid argument in `acc x`");
+    }
+
+    #[test]
+    fn test_dead_code() -> Result<()> {
+        assert_eq!(dead_code(&assemble(INPUT)?), vec![5, 8]);
+        assert_eq!(dead_code(&assemble("jmp +2\nacc +1\njmp +5")?), vec![1]);
+        assert_eq!(dead_code(&assemble("")?), vec![]);
+        assert_eq!(normalise(&assemble("")?), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalise() -> Result<()> {
+        let program = assemble(INPUT)?;
+        let normalised = normalise(&program);
+        assert_eq!(
+            normalised,
+            "L0:
+    nop L0
+L1:
+    acc +1
+    jmp L6
+L3:
+    acc +3
+    jmp L1
+    acc -99     ; unreachable
+L6:
+    acc +1
+    jmp L3
+    acc +6      ; unreachable
+"
+        );
+        assert_eq!(assemble(&normalised)?, program);
+        // out of bounds offsets can't be labelled
+        let program = assemble("jmp +2\njmp -5")?;
+        assert_eq!(
+            normalise(&program),
+            "    jmp end\n    jmp -5      ; unreachable\nend:\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,875 @@
+use crate::bit_grid::{BitGrid, Lattice, WORD_BITS};
+use crate::coor::{Coor, CoorN, Hex};
+use crate::cycle::History;
+use crate::grid::{Connectivity, Grid};
+use crate::sparse_grid::{Bounded, SparseGrid};
+use anyhow::{anyhow, bail, Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::str::FromStr;
+
+pub trait Cell: Clone + PartialEq {
+    fn is_live(&self) -> bool;
+
+    fn with_live(&self, live: bool) -> Self;
+
+    // static cells never change, and line of sight passes through them
+    fn is_static(&self) -> bool {
+        false
+    }
+}
+
+impl Cell for bool {
+    fn is_live(&self) -> bool {
+        *self
+    }
+
+    fn with_live(&self, live: bool) -> Self {
+        live
+    }
+}
+
+pub trait Rule<S> {
+    fn apply(&self, state: &S, live_neighbours: usize) -> S;
+}
+
+impl<S, F> Rule<S> for F
+where
+    F: Fn(&S, usize) -> S,
+{
+    fn apply(&self, state: &S, live_neighbours: usize) -> S {
+        self(state, live_neighbours)
+    }
+}
+
+// a rule in birth/survival notation, e.g. `B3/S23` for Conway's game of life: dead cells with
+// 3 live neighbours become live, live cells with 2 or 3 stay live, all others die
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifeRule {
+    birth: HashSet<usize>,
+    survival: HashSet<usize>,
+}
+
+impl LifeRule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        LifeRule {
+            birth: birth.iter().cloned().collect(),
+            survival: survival.iter().cloned().collect(),
+        }
+    }
+
+    pub fn next(&self, live: bool, live_neighbours: usize) -> bool {
+        if live {
+            self.survival.contains(&live_neighbours)
+        } else {
+            self.birth.contains(&live_neighbours)
+        }
+    }
+}
+
+impl FromStr for LifeRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('/');
+        let mut counts = |prefix: char| -> Result<Vec<usize>> {
+            let part = parts.next().ok_or(anyhow!("rule `{}` too short", s))?;
+            if !part.starts_with(prefix) {
+                bail!("expected `{}` in rule `{}`", prefix, s);
+            }
+            part[1..]
+                .chars()
+                .map(|c| {
+                    c.to_digit(10).map(|d| d as usize).ok_or(anyhow!(
+                        "invalid count `{}` in rule `{}`",
+                        c,
+                        s
+                    ))
+                })
+                .collect()
+        };
+        let birth = counts('B')?;
+        let survival = counts('S')?;
+        if parts.next().is_some() {
+            bail!("rule `{}` too long", s);
+        }
+        Ok(LifeRule::new(&birth, &survival))
+    }
+}
+
+impl<S: Cell> Rule<S> for LifeRule {
+    fn apply(&self, state: &S, live_neighbours: usize) -> S {
+        if state.is_static() {
+            return state.clone();
+        }
+        state.with_live(self.next(state.is_live(), live_neighbours))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Neighbourhood {
+    Adjacent(Connectivity),
+    // the first non-static cell in each direction
+    LineOfSight(Connectivity),
+}
+
+// an automaton on a bounded grid; cells outside the grid count as dead
+pub struct GridAutomaton<S, R> {
+    pub grid: Grid<S>,
+    rule: R,
+    neighbourhood: Neighbourhood,
+    generation: usize,
+}
+
+impl<S: Cell, R: Rule<S>> GridAutomaton<S, R> {
+    pub fn new(grid: Grid<S>, rule: R, neighbourhood: Neighbourhood) -> Self {
+        GridAutomaton {
+            grid,
+            rule,
+            neighbourhood,
+            generation: 0,
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn live_neighbours(&self, coor: &Coor) -> usize {
+        self.live_neighbours_in(&self.grid, coor)
+    }
+
+    fn live_neighbours_in(&self, grid: &Grid<S>, coor: &Coor) -> usize {
+        match self.neighbourhood {
+            Neighbourhood::Adjacent(connectivity) => grid
+                .neighbours(coor, connectivity)
+                .filter(|n| grid[*n].is_live())
+                .count(),
+            Neighbourhood::LineOfSight(connectivity) => connectivity
+                .deltas()
+                .iter()
+                .filter(|&&direction| {
+                    grid.ray(coor, direction)
+                        .map(|(_, cell)| cell)
+                        .find(|cell| !cell.is_static())
+                        .is_some_and(|cell| cell.is_live())
+                })
+                .count(),
+        }
+    }
+
+    fn next_generation(&self, grid: &Grid<S>) -> (Grid<S>, bool) {
+        let mut changed = false;
+        let next = grid.map_with_coor(|coor, cell| {
+            let next = self.rule.apply(cell, self.live_neighbours_in(grid, &coor));
+            changed |= next != *cell;
+            next
+        });
+        (next, changed)
+    }
+
+    // returns whether any cell changed
+    pub fn step(&mut self) -> bool {
+        let (next, changed) = self.next_generation(&self.grid);
+        self.grid = next;
+        self.generation += 1;
+        changed
+    }
+
+    pub fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    // returns the number of generations until nothing changed any more
+    pub fn run_until_stable(&mut self) -> usize {
+        let start = self.generation;
+        while self.step() {}
+        self.generation - start - 1
+    }
+
+    pub fn live(&self) -> usize {
+        self.grid.count(|cell| cell.is_live())
+    }
+}
+
+impl<S: Cell + Eq + Hash, R: Rule<S>> GridAutomaton<S, R> {
+    // steps until a generation repeats (or `limit` generations), leaving the automaton at the
+    // last distinct generation
+    pub fn find_cycle(&mut self, limit: Option<usize>) -> History<Grid<S>> {
+        let history = History::iterate(
+            self.grid.clone(),
+            |grid| self.next_generation(grid).0,
+            limit,
+        );
+        self.grid = history.last().clone();
+        self.generation += history.states().len() - 1;
+        history
+    }
+}
+
+// coordinates with a notion of adjacent cells, defining the shape of an infinite space
+pub trait Topology: Bounded {
+    fn adjacent(&self) -> Vec<Self>;
+}
+
+impl Topology for Coor {
+    fn adjacent(&self) -> Vec<Self> {
+        Connectivity::Eight
+            .deltas()
+            .iter()
+            .map(|&delta| *self + delta)
+            .collect()
+    }
+}
+
+impl<const N: usize> Topology for CoorN<N> {
+    fn adjacent(&self) -> Vec<Self> {
+        self.neighbours().collect()
+    }
+}
+
+impl Topology for Hex {
+    fn adjacent(&self) -> Vec<Self> {
+        self.neighbours().collect()
+    }
+}
+
+// a two-state automaton on an infinite space, only storing the live cells
+pub struct SparseAutomaton<C: Topology, R> {
+    pub live: SparseGrid<C>,
+    rule: R,
+    generation: usize,
+}
+
+impl<C: Topology, R: Rule<bool>> SparseAutomaton<C, R> {
+    pub fn new(live: SparseGrid<C>, rule: R) -> Result<Self> {
+        // with an infinite space, every dead cell would come alive
+        if rule.apply(&false, 0) {
+            bail!("rule makes isolated dead cells live");
+        }
+        Ok(SparseAutomaton {
+            live,
+            rule,
+            generation: 0,
+        })
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn next_generation(&self, live: &SparseGrid<C>) -> SparseGrid<C> {
+        let mut counts = HashMap::new();
+        for coor in live.coors() {
+            for neighbour in coor.adjacent() {
+                *counts.entry(neighbour).or_insert(0) += 1;
+            }
+        }
+        let mut next = SparseGrid::new();
+        for (coor, &count) in counts.iter() {
+            if self.rule.apply(&live.contains(coor), count) {
+                next.insert_point(*coor);
+            }
+        }
+        // live cells without any live neighbours don't show up in the counts
+        for coor in live.coors() {
+            if !counts.contains_key(coor) && self.rule.apply(&true, 0) {
+                next.insert_point(*coor);
+            }
+        }
+        next
+    }
+
+    pub fn step(&mut self) {
+        self.live = self.next_generation(&self.live);
+        self.generation += 1;
+    }
+
+    // steps until a generation repeats (or `limit` generations), leaving the automaton at the
+    // last distinct generation
+    pub fn find_cycle(&mut self, limit: Option<usize>) -> History<SparseGrid<C>> {
+        let history = History::iterate(self.live.clone(), |live| self.next_generation(live), limit);
+        self.live = history.last().clone();
+        self.generation += history.states().len() - 1;
+        history
+    }
+
+    pub fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.live.len()
+    }
+}
+
+// a two-state automaton on Z^N whose state is symmetric under negating any component from
+// `axis` on. only the cells with those components non-negative are stored, each standing in for
+// all of its mirror images
+pub struct MirroredAutomaton<const N: usize, R> {
+    live: SparseGrid<CoorN<N>>,
+    axis: usize,
+    rule: R,
+    generation: usize,
+}
+
+impl<const N: usize, R: Rule<bool>> MirroredAutomaton<N, R> {
+    pub fn new(live: SparseGrid<CoorN<N>>, axis: usize, rule: R) -> Result<Self> {
+        if rule.apply(&false, 0) {
+            bail!("rule makes isolated dead cells live");
+        }
+        let mut automaton = MirroredAutomaton {
+            live: SparseGrid::new(),
+            axis,
+            rule,
+            generation: 0,
+        };
+        automaton.live = live.coors().map(|c| automaton.fold(c)).collect();
+        if automaton.population() != live.len() {
+            bail!("cells aren't symmetric in the axes from {}", axis);
+        }
+        Ok(automaton)
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    // the mirror image in the stored orthant
+    fn fold(&self, coor: &CoorN<N>) -> CoorN<N> {
+        let mut res = *coor;
+        for c in res.0[self.axis..].iter_mut() {
+            *c = c.abs();
+        }
+        res
+    }
+
+    // how many cells `coor` stands in for
+    fn multiplicity(&self, coor: &CoorN<N>) -> usize {
+        1 << coor.0[self.axis..].iter().filter(|&&c| c != 0).count()
+    }
+
+    fn next_generation(&self, live: &SparseGrid<CoorN<N>>) -> SparseGrid<CoorN<N>> {
+        // pushing each cell's multiplicity to the folded neighbours over-counts the neighbours
+        // of a cell by exactly its own multiplicity
+        let mut counts = HashMap::new();
+        for coor in live.coors() {
+            let multiplicity = self.multiplicity(coor);
+            for neighbour in coor.neighbours() {
+                *counts.entry(self.fold(&neighbour)).or_insert(0) += multiplicity;
+            }
+        }
+        let mut next = SparseGrid::new();
+        for (coor, &count) in counts.iter() {
+            let count = count / self.multiplicity(coor);
+            if self.rule.apply(&live.contains(coor), count) {
+                next.insert_point(*coor);
+            }
+        }
+        for coor in live.coors() {
+            if !counts.contains_key(coor) && self.rule.apply(&true, 0) {
+                next.insert_point(*coor);
+            }
+        }
+        next
+    }
+
+    pub fn step(&mut self) {
+        self.live = self.next_generation(&self.live);
+        self.generation += 1;
+    }
+
+    pub fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.live.coors().map(|c| self.multiplicity(c)).sum()
+    }
+
+    // the stored cells, without their mirror images
+    pub fn orthant(&self) -> &SparseGrid<CoorN<N>> {
+        &self.live
+    }
+
+    // every live cell, including the mirror images
+    pub fn live(&self) -> SparseGrid<CoorN<N>> {
+        let mut res = SparseGrid::new();
+        for coor in self.live.coors() {
+            let mut images = vec![*coor];
+            for axis in self.axis..N {
+                if coor.0[axis] != 0 {
+                    let mirrored = images
+                        .iter()
+                        .map(|image| {
+                            let mut mirrored = *image;
+                            mirrored.0[axis] = -mirrored.0[axis];
+                            mirrored
+                        })
+                        .collect::<Vec<_>>();
+                    images.extend(mirrored);
+                }
+            }
+            for image in images {
+                res.insert_point(image);
+            }
+        }
+        res
+    }
+}
+
+// which storage an automaton on an infinite space uses
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Backend {
+    // a hash set of live cells; cheap for a few cells spread far apart
+    #[default]
+    Sparse,
+    // a bit per cell in a box around the live cells; fast for crowded spaces
+    Dense,
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "sparse" => Backend::Sparse,
+            "dense" => Backend::Dense,
+            _ => bail!("unknown backend `{}`", s),
+        })
+    }
+}
+
+// how many cells to leave around the live ones when the box is regrown, so it isn't regrown
+// on every generation
+const DENSE_MARGIN: usize = 4;
+
+// a two-state automaton on an infinite lattice, storing the cells in a box around the live
+// ones as bits. neighbour counts are kept bit-sliced, adding up a whole word of cells at a time
+pub struct DenseAutomaton<C> {
+    cells: BitGrid<C>,
+    // the neighbours of the origin, as components
+    offsets: Vec<Vec<i64>>,
+    // the neighbour counts which make a dead cell live, and which keep a live cell live
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+    generation: usize,
+}
+
+impl<C: Topology + Lattice> DenseAutomaton<C> {
+    pub fn new<R: Rule<bool>>(live: &SparseGrid<C>, rule: R) -> Result<Self> {
+        if rule.apply(&false, 0) {
+            bail!("rule makes isolated dead cells live");
+        }
+        let origin = C::from_components(&vec![0; C::DIMS]);
+        let offsets = origin
+            .adjacent()
+            .iter()
+            .map(|n| {
+                (0..C::DIMS)
+                    .map(|axis| n.component(axis))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        if offsets.iter().flatten().any(|c| c.abs() > 1) {
+            bail!("only neighbours differing by at most one in every component are supported");
+        }
+        if offsets.len() >= 1 << MAX_PLANES {
+            bail!("too many neighbours ({})", offsets.len());
+        }
+        let counts = 0..=offsets.len();
+        Ok(DenseAutomaton {
+            cells: live.coors().cloned().collect(),
+            birth: counts.clone().filter(|&n| rule.apply(&false, n)).collect(),
+            survival: counts.filter(|&n| rule.apply(&true, n)).collect(),
+            offsets,
+            generation: 0,
+        })
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn cells(&self) -> &BitGrid<C> {
+        &self.cells
+    }
+
+    fn next_generation(&self, cells: &BitGrid<C>) -> BitGrid<C> {
+        // cells can only come alive next to live ones, and their neighbours need to be inside
+        // the box as well
+        let regrown;
+        let cells = if cells.has_margin(2) {
+            cells
+        } else {
+            regrown = cells.with_margin(DENSE_MARGIN);
+            &regrown
+        };
+        let extents = cells.extents();
+        let planes = usize::BITS as usize - self.offsets.len().leading_zeros() as usize;
+        // each neighbour as a number of rows to move, and a shift within the row
+        let moves = self
+            .offsets
+            .iter()
+            .map(|offset| {
+                let rows = (1..C::DIMS)
+                    .map(|axis| offset[axis] as isize * cells.row_stride(axis) as isize)
+                    .sum::<isize>();
+                (rows, offset[0])
+            })
+            .collect::<Vec<_>>();
+
+        let mut next = BitGrid::new(cells.origin().to_vec(), extents.to_vec());
+        let mut counts = vec![[0; MAX_PLANES]; cells.words_per_row()];
+        for row in 0..cells.rows() {
+            // the outermost rows stay empty
+            let components = cells.row_components(row);
+            if (1..C::DIMS)
+                .any(|axis| components[axis] == 0 || components[axis] + 1 == extents[axis])
+            {
+                continue;
+            }
+            for count in counts.iter_mut() {
+                *count = [0; MAX_PLANES];
+            }
+            for &(rows, shift) in &moves {
+                let neighbours = cells.row((row as isize + rows) as usize);
+                for (w, count) in counts.iter_mut().enumerate() {
+                    add_word(&mut count[..planes], shifted_word(neighbours, w, shift));
+                }
+            }
+            for ((word, live), count) in next
+                .row_mut(row)
+                .iter_mut()
+                .zip(cells.row(row))
+                .zip(counts.iter())
+            {
+                let count = &count[..planes];
+                let born = self
+                    .birth
+                    .iter()
+                    .fold(0, |acc, &n| acc | count_is(count, n));
+                let survived = self
+                    .survival
+                    .iter()
+                    .fold(0, |acc, &n| acc | count_is(count, n));
+                *word = (born & !live) | (survived & live);
+            }
+        }
+        next
+    }
+
+    pub fn step(&mut self) {
+        self.cells = self.next_generation(&self.cells);
+        self.generation += 1;
+    }
+
+    pub fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn live(&self) -> SparseGrid<C> {
+        (&self.cells).into()
+    }
+}
+
+// enough bit planes to count up to 65535 neighbours
+const MAX_PLANES: usize = 16;
+
+// bit i of the result is the cell `shift` places along from bit i of word `w`
+fn shifted_word(row: &[u64], w: usize, shift: i64) -> u64 {
+    match shift {
+        0 => row[w],
+        1 => row[w] >> 1 | row.get(w + 1).map_or(0, |next| next << (WORD_BITS - 1)),
+        -1 => {
+            row[w] << 1
+                | w.checked_sub(1)
+                    .map_or(0, |prev| row[prev] >> (WORD_BITS - 1))
+        }
+        _ => unreachable!("neighbours are at most one apart"),
+    }
+}
+
+// adds one to the bit-sliced counters for every bit set in `word`. plane i holds bit i of each
+// counter
+fn add_word(planes: &mut [u64], word: u64) {
+    let mut carry = word;
+    for plane in planes.iter_mut() {
+        if carry == 0 {
+            break;
+        }
+        let overflow = *plane & carry;
+        *plane ^= carry;
+        carry = overflow;
+    }
+}
+
+// the bits whose counter equals `n`
+fn count_is(planes: &[u64], n: usize) -> u64 {
+    planes.iter().enumerate().fold(!0, |acc, (bit, &plane)| {
+        acc & if n >> bit & 1 == 1 { plane } else { !plane }
+    })
+}
+
+// an automaton on an infinite space with the backend picked at runtime
+pub enum InfiniteAutomaton<C: Topology, R> {
+    Sparse(SparseAutomaton<C, R>),
+    Dense(DenseAutomaton<C>),
+}
+
+impl<C: Topology + Lattice, R: Rule<bool>> InfiniteAutomaton<C, R> {
+    pub fn new(backend: Backend, live: SparseGrid<C>, rule: R) -> Result<Self> {
+        Ok(match backend {
+            Backend::Sparse => InfiniteAutomaton::Sparse(SparseAutomaton::new(live, rule)?),
+            Backend::Dense => InfiniteAutomaton::Dense(DenseAutomaton::new(&live, rule)?),
+        })
+    }
+
+    pub fn generation(&self) -> usize {
+        match self {
+            InfiniteAutomaton::Sparse(automaton) => automaton.generation(),
+            InfiniteAutomaton::Dense(automaton) => automaton.generation(),
+        }
+    }
+
+    pub fn step(&mut self) {
+        match self {
+            InfiniteAutomaton::Sparse(automaton) => automaton.step(),
+            InfiniteAutomaton::Dense(automaton) => automaton.step(),
+        }
+    }
+
+    pub fn run(&mut self, generations: usize) {
+        match self {
+            InfiniteAutomaton::Sparse(automaton) => automaton.run(generations),
+            InfiniteAutomaton::Dense(automaton) => automaton.run(generations),
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        match self {
+            InfiniteAutomaton::Sparse(automaton) => automaton.population(),
+            InfiniteAutomaton::Dense(automaton) => automaton.population(),
+        }
+    }
+
+    pub fn live(&self) -> SparseGrid<C> {
+        match self {
+            InfiniteAutomaton::Sparse(automaton) => automaton.live.clone(),
+            InfiniteAutomaton::Dense(automaton) => automaton.live(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() -> Result<()> {
+        let rule: LifeRule = "B3/S23".parse()?;
+        assert_eq!(rule, LifeRule::new(&[3], &[2, 3]));
+        assert!(rule.next(false, 3));
+        assert!(!rule.next(false, 2));
+        assert!(rule.next(true, 2));
+        assert!(!rule.next(true, 4));
+        assert!("S23/B3".parse::<LifeRule>().is_err());
+        assert!("B3/S2x".parse::<LifeRule>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blinker() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(".....\n..#..\n..#..\n..#..\n.....")?;
+        let cells = grid.map(|&c| c == '#');
+        let mut automaton = GridAutomaton::new(
+            cells.clone(),
+            "B3/S23".parse::<LifeRule>()?,
+            Neighbourhood::Adjacent(Connectivity::Eight),
+        );
+        automaton.step();
+        assert!(automaton.grid[Coor::new(1, 2)]);
+        assert!(!automaton.grid[Coor::new(2, 1)]);
+        automaton.step();
+        assert_eq!(automaton.grid, cells);
+        assert_eq!(automaton.generation(), 2);
+
+        let mut sparse = SparseAutomaton::new(
+            cells
+                .iter()
+                .filter(|(_, &c)| c)
+                .map(|(coor, _)| coor)
+                .collect(),
+            "B3/S23".parse::<LifeRule>()?,
+        )?;
+        sparse.step();
+        assert_eq!(sparse.population(), 3);
+        assert!(sparse.live.contains(&Coor::new(1, 2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_closure_rule() -> Result<()> {
+        // every live cell dies, every cell next to a live one is born
+        let rule = |&live: &bool, n: usize| !live && n > 0;
+        let mut sparse = SparseAutomaton::new([Hex::default()].iter().cloned().collect(), rule)?;
+        sparse.step();
+        assert_eq!(sparse.population(), 6);
+        assert!(SparseAutomaton::new(SparseGrid::<Hex>::new(), |_: &bool, _| true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cycle() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(".....\n..#..\n..#..\n..#..\n.....")?;
+        let mut automaton = GridAutomaton::new(
+            grid.map(|&c| c == '#'),
+            "B3/S23".parse::<LifeRule>()?,
+            Neighbourhood::Adjacent(Connectivity::Eight),
+        );
+        let history = automaton.find_cycle(None);
+        let cycle = history.cycle().expect("blinker oscillates");
+        assert_eq!((cycle.offset, cycle.period), (0, 2));
+        assert_eq!(history.state_at(1001), Some(&automaton.grid));
+
+        // a glider moves, so only the shape repeats
+        let glider: SparseGrid<Coor> = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+            .iter()
+            .map(|&(x, y)| Coor::new(x, y))
+            .collect();
+        let mut sparse = SparseAutomaton::new(glider, "B3/S23".parse::<LifeRule>()?)?;
+        let history = sparse.find_cycle(Some(20));
+        assert_eq!(history.cycle(), None);
+        assert_eq!(sparse.generation(), 20);
+        assert_eq!(sparse.population(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stable() -> Result<()> {
+        // a block is a still life
+        let grid: Grid<char> = Grid::parse("....\n.##.\n.##.\n....")?;
+        let mut automaton = GridAutomaton::new(
+            grid.map(|&c| c == '#'),
+            "B3/S23".parse::<LifeRule>()?,
+            Neighbourhood::LineOfSight(Connectivity::Eight),
+        );
+        assert_eq!(automaton.run_until_stable(), 0);
+        assert_eq!(automaton.live(), 4);
+        Ok(())
+    }
+
+    // steps both backends side by side, checking they agree on every generation
+    fn cross_check<C: Topology + Lattice + std::fmt::Debug>(
+        live: SparseGrid<C>,
+        rule: &str,
+        generations: usize,
+    ) -> Result<usize> {
+        let mut sparse = SparseAutomaton::new(live.clone(), rule.parse::<LifeRule>()?)?;
+        let mut dense = DenseAutomaton::new(&live, rule.parse::<LifeRule>()?)?;
+        for _ in 0..generations {
+            sparse.step();
+            dense.step();
+            assert_eq!(dense.live(), sparse.live);
+        }
+        Ok(dense.population())
+    }
+
+    #[test]
+    fn test_dense_matches_sparse() -> Result<()> {
+        let glider: SparseGrid<Coor> = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+            .iter()
+            .map(|&(x, y)| Coor::new(x, y))
+            .collect();
+        // the glider crosses word boundaries and forces the box to regrow
+        assert_eq!(cross_check(glider.clone(), "B3/S23", 300)?, 5);
+        assert_eq!(cross_check(glider, "B36/S23", 50)?, 5);
+
+        // an R-pentomino grows chaotically
+        let r_pentomino: SparseGrid<Coor> = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]
+            .iter()
+            .map(|&(x, y)| Coor::new(x, y))
+            .collect();
+        cross_check(r_pentomino, "B3/S23", 100)?;
+
+        let cubes: SparseGrid<CoorN<4>> = [
+            [1, 0, 0, 0],
+            [2, 1, 0, 0],
+            [0, 2, 0, 0],
+            [1, 2, 0, 0],
+            [2, 2, 0, 0],
+        ]
+        .iter()
+        .map(|&c| CoorN::new(c))
+        .collect();
+        assert_eq!(cross_check(cubes, "B3/S23", 6)?, 848);
+
+        let hexes: SparseGrid<Hex> = [Hex::default(), Hex::new(1, -1, 0), Hex::new(3, 0, -3)]
+            .iter()
+            .cloned()
+            .collect();
+        cross_check(hexes, "B2/S12", 40)?;
+
+        let backend: Backend = "dense".parse()?;
+        let mut automaton = InfiniteAutomaton::new(
+            backend,
+            SparseGrid::<Hex>::new(),
+            "B2/S12".parse::<LifeRule>()?,
+        )?;
+        automaton.run(3);
+        assert_eq!(automaton.population(), 0);
+        assert!("hashed".parse::<Backend>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mirrored_matches_full() -> Result<()> {
+        let slice = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for &generations in &[1, 2, 6] {
+            let cubes: SparseGrid<CoorN<4>> = slice
+                .iter()
+                .map(|&(x, y)| CoorN::new([x, y, 0, 0]))
+                .collect();
+            let mut full = SparseAutomaton::new(cubes.clone(), "B3/S23".parse::<LifeRule>()?)?;
+            let mut mirrored = MirroredAutomaton::new(cubes, 2, "B3/S23".parse::<LifeRule>()?)?;
+            full.run(generations);
+            mirrored.run(generations);
+            assert_eq!(mirrored.live(), full.live);
+            assert_eq!(mirrored.population(), full.population());
+            assert!(mirrored.orthant().len() < full.population());
+        }
+
+        // a 3D plus sign is symmetric in every axis
+        let cubes: SparseGrid<CoorN<3>> = (0..3)
+            .flat_map(|axis| {
+                [-1, 1].iter().map(move |&c| {
+                    let mut coor = CoorN::default();
+                    coor.0[axis] = c;
+                    coor
+                })
+            })
+            .chain(std::iter::once(CoorN::default()))
+            .collect();
+        let mut full = SparseAutomaton::new(cubes.clone(), "B3/S23".parse::<LifeRule>()?)?;
+        let mut mirrored = MirroredAutomaton::new(cubes, 0, "B3/S23".parse::<LifeRule>()?)?;
+        full.run(4);
+        mirrored.run(4);
+        assert_eq!(mirrored.live(), full.live);
+
+        let lopsided: SparseGrid<CoorN<3>> = [CoorN::new([0, 0, 1])].iter().cloned().collect();
+        assert!(MirroredAutomaton::new(lopsided, 2, "B3/S23".parse::<LifeRule>()?).is_err());
+        Ok(())
+    }
+}
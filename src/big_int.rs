@@ -0,0 +1,576 @@
+use anyhow::{bail, Error, Result};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::{Product, Sum};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+// magnitudes are little endian base 2^32 digits without trailing zeros, so zero is empty
+
+fn trim(mut digits: Vec<u32>) -> Vec<u32> {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    digits
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut res = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (i, &d) in long.iter().enumerate() {
+        let sum = d as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        res.push(sum as u32);
+        carry = sum >> 32;
+    }
+    res.push(carry as u32);
+    trim(res)
+}
+
+// a - b, for a >= b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &d) in a.iter().enumerate() {
+        let diff = d as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = (diff < 0) as i64;
+        res.push((diff + (borrow << 32)) as u32);
+    }
+    trim(res)
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut res = vec![0; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            // at most (2^32 - 1)^2 + 2 * (2^32 - 1), which fits
+            let cur = res[i + j] as u64 + x as u64 * y as u64 + carry;
+            res[i + j] = cur as u32;
+            carry = cur >> 32;
+        }
+        res[i + b.len()] = carry as u32;
+    }
+    trim(res)
+}
+
+// a * m + c
+fn mul_add_small(a: &[u32], m: u32, c: u32) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len() + 1);
+    let mut carry = c as u64;
+    for &d in a {
+        let cur = d as u64 * m as u64 + carry;
+        res.push(cur as u32);
+        carry = cur >> 32;
+    }
+    res.push(carry as u32);
+    trim(res)
+}
+
+fn div_rem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0; a.len()];
+    let mut rem = 0u64;
+    for (i, &digit) in a.iter().enumerate().rev() {
+        let cur = (rem << 32) | digit as u64;
+        quotient[i] = (cur / d as u64) as u32;
+        rem = cur % d as u64;
+    }
+    (trim(quotient), rem as u32)
+}
+
+// long division a bit at a time, for divisors wider than a digit
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    assert!(!b.is_empty(), "attempt to divide by zero");
+    if b.len() == 1 {
+        let (q, r) = div_rem_small(a, b[0]);
+        return (q, trim(vec![r]));
+    }
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    let mut quotient = vec![0; a.len()];
+    let mut rem: Vec<u32> = vec![];
+    for bit in (0..a.len() * 32).rev() {
+        rem = mul_add_small(&rem, 2, (a[bit / 32] >> (bit % 32)) & 1);
+        if cmp_magnitude(&rem, b) != Ordering::Less {
+            rem = sub_magnitude(&rem, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    (trim(quotient), rem)
+}
+
+// an arbitrary precision integer
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, magnitude: Vec<u32>) -> Self {
+        let magnitude = trim(magnitude);
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn zero() -> Self {
+        BigInt::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt::from_parts(false, self.magnitude.clone())
+    }
+
+    // `None` if it doesn't fit
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let abs = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &d| (acc << 32) | d as u64);
+        if self.negative {
+            0i64.checked_sub_unsigned(abs)
+        } else {
+            i64::try_from(abs).ok()
+        }
+    }
+
+    // the quotient rounded towards zero and a remainder with the sign of `self`, like `/` and
+    // `%` on primitive integers. panics if `other` is zero
+    pub fn div_rem(&self, other: &Self) -> (Self, Self) {
+        let (q, r) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+        (
+            BigInt::from_parts(self.negative != other.negative, q),
+            BigInt::from_parts(self.negative, r),
+        )
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        let abs = n.unsigned_abs();
+        BigInt::from_parts(n < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() {
+            bail!("invalid integer `{}`", s);
+        }
+        let mut magnitude = vec![];
+        for c in digits.chars() {
+            match c.to_digit(10) {
+                Some(d) => magnitude = mul_add_small(&magnitude, 10, d),
+                None => bail!("invalid integer `{}`", s),
+            }
+        }
+        Ok(BigInt::from_parts(negative, magnitude))
+    }
+}
+
+// the largest power of ten in a digit
+const CHUNK: u32 = 1_000_000_000;
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // nine decimal digits at a time, least significant first
+        let mut chunks = vec![];
+        let mut rest = self.magnitude.clone();
+        while !rest.is_empty() {
+            let (q, r) = div_rem_small(&rest, CHUNK);
+            chunks.push(r);
+            rest = q;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().expect("non-zero has a chunk"))?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -self.clone()
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+        // the sign of whichever is bigger wins
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::from_parts(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl Div for &BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem for &BigInt {
+    type Output = BigInt;
+
+    fn rem(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).1
+    }
+}
+
+// the operators on owned values borrow
+macro_rules! forward_owned {
+    ($($op:ident $method:ident),*) => {
+        $(
+            impl $op for BigInt {
+                type Output = BigInt;
+
+                fn $method(self, other: BigInt) -> BigInt {
+                    (&self).$method(&other)
+                }
+            }
+        )*
+    };
+}
+
+forward_owned!(Add add, Sub sub, Mul mul, Div div, Rem rem);
+
+// an i64 until arithmetic on it would overflow, and a `BigInt` from then on. results that fit
+// in an i64 always go back to being `Small`
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Integer {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl Integer {
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Integer::Small(n) => Some(*n),
+            Integer::Big(_) => None,
+        }
+    }
+
+    pub fn to_big(&self) -> BigInt {
+        match self {
+            Integer::Small(n) => BigInt::from(*n),
+            Integer::Big(n) => n.clone(),
+        }
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(n: i64) -> Self {
+        Integer::Small(n)
+    }
+}
+
+impl From<BigInt> for Integer {
+    fn from(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(n) => Integer::Small(n),
+            None => Integer::Big(n),
+        }
+    }
+}
+
+impl FromStr for Integer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(s.parse::<BigInt>()?.into())
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Small(n) => write!(f, "{}", n),
+            Integer::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl PartialEq<i64> for Integer {
+    fn eq(&self, other: &i64) -> bool {
+        self.to_i64() == Some(*other)
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Integer::Small(a), Integer::Small(b)) => a.cmp(b),
+            _ => self.to_big().cmp(&other.to_big()),
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for Integer {
+    type Output = Integer;
+
+    fn neg(self) -> Integer {
+        match self {
+            Integer::Small(n) => match n.checked_neg() {
+                Some(n) => Integer::Small(n),
+                None => Integer::Big(-BigInt::from(n)),
+            },
+            Integer::Big(n) => (-n).into(),
+        }
+    }
+}
+
+// checked on i64s first, redone on `BigInt`s if that overflows. dividing by zero still panics
+macro_rules! impl_integer_op {
+    ($($op:ident $method:ident $checked:ident),*) => {
+        $(
+            impl $op for Integer {
+                type Output = Integer;
+
+                fn $method(self, other: Integer) -> Integer {
+                    if let (Integer::Small(a), Integer::Small(b)) = (&self, &other) {
+                        if let Some(n) = a.$checked(*b) {
+                            return Integer::Small(n);
+                        }
+                    }
+                    self.to_big().$method(other.to_big()).into()
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_op!(
+    Add add checked_add,
+    Sub sub checked_sub,
+    Mul mul checked_mul,
+    Div div checked_div,
+    Rem rem checked_rem
+);
+
+impl AddAssign for Integer {
+    fn add_assign(&mut self, other: Integer) {
+        *self = std::mem::replace(self, Integer::Small(0)) + other;
+    }
+}
+
+impl Sum for Integer {
+    fn sum<I: Iterator<Item = Integer>>(iter: I) -> Self {
+        iter.fold(Integer::Small(0), |acc, n| acc + n)
+    }
+}
+
+impl Product for Integer {
+    fn product<I: Iterator<Item = Integer>>(iter: I) -> Self {
+        iter.fold(Integer::Small(1), |acc, n| acc * n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for s in &[
+            "0",
+            "7",
+            "-7",
+            "4294967296",
+            "-9223372036854775808",
+            "123456789012345678901234567890",
+            "-1000000000000000000000000000001",
+        ] {
+            assert_eq!(big(s).to_string(), *s);
+        }
+        assert_eq!(big("+0042").to_string(), "42");
+        assert_eq!(big("-0"), BigInt::zero());
+        assert!("".parse::<BigInt>().is_err());
+        assert!("-".parse::<BigInt>().is_err());
+        assert!("12a".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn test_matches_i128() {
+        let values: Vec<i64> = vec![
+            0,
+            1,
+            -1,
+            2,
+            -3,
+            97,
+            -1000,
+            u32::MAX as i64,
+            -(u32::MAX as i64) - 1,
+            1 << 40,
+            -(1 << 45) + 7,
+            i64::MAX,
+            i64::MIN,
+            i64::MIN + 1,
+        ];
+        for &a in &values {
+            for &b in &values {
+                let (x, y) = (BigInt::from(a), BigInt::from(b));
+                let (a, b) = (a as i128, b as i128);
+                assert_eq!((&x + &y).to_string(), (a + b).to_string());
+                assert_eq!((&x - &y).to_string(), (a - b).to_string());
+                assert_eq!((&x * &y).to_string(), (a * b).to_string());
+                assert_eq!(x.cmp(&y), a.cmp(&b));
+                if b != 0 {
+                    let (q, r) = x.div_rem(&y);
+                    assert_eq!(q.to_string(), (a / b).to_string(), "{} / {}", a, b);
+                    assert_eq!(r.to_string(), (a % b).to_string(), "{} % {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_division() {
+        let a = big("123456789012345678901234567890123456789");
+        let b = big("-98765432109876543210");
+        let (q, r) = a.div_rem(&b);
+        assert_eq!(q, big("-1249999988609375000"));
+        assert_eq!(&(&q * &b) + &r, a);
+        assert!(r.abs() < b.abs());
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!((-BigInt::from(i64::MIN)).to_i64(), None);
+    }
+
+    #[test]
+    fn test_integer_falls_back() -> Result<()> {
+        let max = Integer::from(i64::MAX);
+        let sum = max.clone() + Integer::from(1);
+        assert!(matches!(sum, Integer::Big(_)));
+        assert_eq!(sum.to_string(), "9223372036854775808");
+        // and comes back once it fits again
+        assert_eq!(sum - Integer::from(2), i64::MAX - 1);
+        assert_eq!(
+            -Integer::from(i64::MIN),
+            "9223372036854775808".parse::<Integer>()?
+        );
+        let product: Integer = (1..=30).map(Integer::from).product();
+        assert_eq!(product.to_string(), "265252859812191058636308480000000");
+        assert_eq!(
+            product / Integer::from(1 << 40) % Integer::from(1000),
+            Integer::from(565)
+        );
+        let mut total = Integer::from(i64::MAX);
+        total += Integer::from(i64::MAX);
+        assert_eq!(total.to_string(), "18446744073709551614");
+        let square = max.clone() * max.clone();
+        assert!(square > max && square.to_i64().is_none());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{bail, Error, Result};
 use aoc2020::coor::Coor;
 use aoc2020::dispatch;
+use aoc2020::grid::Grid;
 use std::convert::TryFrom;
 
 #[derive(Debug, Clone, Copy)]
@@ -23,29 +24,22 @@ impl TryFrom<char> for Tile {
 
 #[derive(Debug)]
 struct Map {
-    tiles: Vec<Vec<Tile>>,
+    tiles: Grid<Tile>,
 }
 
 impl Map {
     fn new(input: &str) -> Result<Self> {
-        let mut tiles = vec![];
-        for row in input.split('\n') {
-            let mut row_tiles = vec![];
-            for c in row.chars() {
-                row_tiles.push(Tile::try_from(c)?);
-            }
-            tiles.push(row_tiles);
-        }
-        Ok(Map { tiles })
+        Ok(Map {
+            tiles: Grid::parse(input)?,
+        })
     }
 
     fn get_at(&self, pos: Coor) -> Option<Tile> {
-        let row = self.tiles.get(pos.y as usize)?;
-        if row.len() == 0 {
-            None
-        } else {
-            Some(row[pos.x as usize % row.len()])
+        // the map repeats to the right, but not downwards
+        if pos.y >= self.tiles.height() as i64 {
+            return None;
         }
+        self.tiles.get_wrapping(&pos).copied()
     }
 
     fn traverse(&self, step: Coor) -> usize {
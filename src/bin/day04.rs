@@ -1,14 +1,23 @@
 use anyhow::{anyhow, bail, Error, Result};
-use aoc2020::dispatch;
+use aoc2020::shared::{dispatch_with, Answer, Opt, Options};
 use lazy_static::lazy_static;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
+use std::fmt;
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(
+        |input, options| with_report(input, options, false, part1),
+        |input, options| with_report(input, options, true, part2),
+        &[Opt {
+            name: "report",
+            help: "List what's wrong with each passport, and count each kind of problem",
+            value: None,
+        }],
+    )
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 enum Field {
     Byr,
     Iyr,
@@ -38,6 +47,23 @@ impl TryFrom<&str> for Field {
     }
 }
 
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Field::*;
+        let name = match self {
+            Byr => "byr",
+            Iyr => "iyr",
+            Eyr => "eyr",
+            Hgt => "hgt",
+            Hcl => "hcl",
+            Ecl => "ecl",
+            Pid => "pid",
+            Cid => "cid",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 type Passport<'a> = HashMap<Field, &'a str>;
 
 lazy_static! {
@@ -66,7 +92,7 @@ fn parse_entry(raw_entry: &str) -> Result<(Field, &str)> {
     Ok((key, value))
 }
 
-fn parse_passport(raw_passport: &str) -> Result<Passport> {
+fn parse_passport(raw_passport: &str) -> Result<Passport<'_>> {
     Ok(raw_passport
         .split_whitespace()
         .map(parse_entry)
@@ -85,80 +111,243 @@ impl TryFrom<&str> for Unit {
     type Error = Error;
 
     fn try_from(s: &str) -> Result<Self> {
-        match &s[s.len() - 2..] {
-            "cm" => Ok(Unit::Cm),
-            "in" => Ok(Unit::In),
-            _ => bail!("Invalid unit"),
+        if s.ends_with("cm") {
+            Ok(Unit::Cm)
+        } else if s.ends_with("in") {
+            Ok(Unit::In)
+        } else {
+            bail!("Invalid unit")
+        }
+    }
+}
+
+// why a present field's value is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Invalid {
+    NotANumber(String),
+    OutOfRange { value: i32, min: i32, max: i32 },
+    BadUnit(String),
+    BadHairColour(String),
+    BadEyeColour(String),
+    BadPassportId(String),
+}
+
+impl Invalid {
+    // for grouping failures in the summary
+    fn kind(&self) -> &'static str {
+        match self {
+            Invalid::NotANumber(_) => "not a number",
+            Invalid::OutOfRange { .. } => "out of range",
+            Invalid::BadUnit(_) => "bad unit",
+            Invalid::BadHairColour(_) => "malformed hair colour",
+            Invalid::BadEyeColour(_) => "unknown eye colour",
+            Invalid::BadPassportId(_) => "malformed passport id",
         }
     }
 }
 
-fn is_valid_entry(key: Field, value: &str) -> Result<()> {
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Invalid::NotANumber(s) => write!(f, "`{}` isn't a number", s),
+            Invalid::OutOfRange { value, min, max } => {
+                write!(f, "out of range: {} isn't in {}..={}", value, min, max)
+            }
+            Invalid::BadUnit(s) => write!(f, "bad unit: `{}` isn't in cm or in", s),
+            Invalid::BadHairColour(s) => {
+                write!(
+                    f,
+                    "malformed hair colour: `{}` isn't # and six hex digits",
+                    s
+                )
+            }
+            Invalid::BadEyeColour(s) => write!(f, "unknown eye colour `{}`", s),
+            Invalid::BadPassportId(s) => {
+                write!(f, "malformed passport id: `{}` isn't nine digits", s)
+            }
+        }
+    }
+}
+
+fn is_valid_entry(key: Field, value: &str) -> std::result::Result<(), Invalid> {
     use Field::*;
 
-    let int_range = |s: &str, min: i32, max: i32| -> Result<()> {
-        let val: i32 = s.parse()?;
+    let int_range = |s: &str, min: i32, max: i32| {
+        let val: i32 = s.parse().map_err(|_| Invalid::NotANumber(s.into()))?;
         if val < min || val > max {
-            bail!("{:?} `{}` outside range", key, value);
+            return Err(Invalid::OutOfRange {
+                value: val,
+                min,
+                max,
+            });
         }
         Ok(())
     };
 
-    Ok(match key {
-        Byr => int_range(value, 1920, 2002)?,
-        Iyr => int_range(value, 2010, 2020)?,
-        Eyr => int_range(value, 2020, 2030)?,
+    match key {
+        Byr => int_range(value, 1920, 2002),
+        Iyr => int_range(value, 2010, 2020),
+        Eyr => int_range(value, 2020, 2030),
         Hgt => {
-            let unit = Unit::try_from(value)?;
+            let unit = Unit::try_from(value).map_err(|_| Invalid::BadUnit(value.into()))?;
             let amount = &value[..value.len() - 2];
             match unit {
-                Unit::Cm => int_range(amount, 150, 193)?,
-                Unit::In => int_range(amount, 59, 76)?,
-            }
-        }
-        Hcl => {
-            if !value.starts_with("#") {
-                bail!("bad hcl start");
+                Unit::Cm => int_range(amount, 150, 193),
+                Unit::In => int_range(amount, 59, 76),
             }
-            i64::from_str_radix(&value[1..], 16)?;
         }
-        Ecl => {
-            if !matches!(value, "amb" | "blu" | "brn" | "gry" | "grn" | "hzl" | "oth") {
-                bail!("bad hcl");
+        Hcl => match value.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(()),
+            _ => Err(Invalid::BadHairColour(value.into())),
+        },
+        Ecl => match value {
+            "amb" | "blu" | "brn" | "gry" | "grn" | "hzl" | "oth" => Ok(()),
+            _ => Err(Invalid::BadEyeColour(value.into())),
+        },
+        Pid => {
+            if value.len() != 9 || !value.chars().all(|c| c.is_ascii_digit()) {
+                return Err(Invalid::BadPassportId(value.into()));
             }
+            Ok(())
         }
+        Cid => Ok(()),
+    }
+}
 
-        Pid => {
-            if value.len() != 9 {
-                bail!("bad pid len");
-            }
-            value.trim_start_matches(|c| c == '0').parse::<i64>()?;
+// everything wrong with a passport, in field order
+#[derive(Debug, Default, PartialEq)]
+struct Diagnosis {
+    // entries that couldn't be parsed, with why
+    unreadable: Vec<(String, String)>,
+    missing: Vec<Field>,
+    invalid: Vec<(Field, Invalid)>,
+}
+
+impl Diagnosis {
+    fn is_ok(&self) -> bool {
+        self.unreadable.is_empty() && self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "valid");
+        }
+        let mut problems = self
+            .unreadable
+            .iter()
+            .map(|(entry, error)| format!("unreadable `{}`: {}", entry, error))
+            .collect::<Vec<_>>();
+        if !self.missing.is_empty() {
+            let missing = self.missing.iter().map(|field| field.to_string());
+            problems.push(format!(
+                "missing {}",
+                missing.collect::<Vec<_>>().join(", ")
+            ));
         }
-        Cid => {}
-    })
+        for (field, invalid) in &self.invalid {
+            problems.push(format!("{}: {}", field, invalid));
+        }
+        write!(f, "{}", problems.join("; "))
+    }
+}
+
+// with `check_values`, also validates the fields that are there
+fn diagnose(passport: &Passport, check_values: bool) -> Diagnosis {
+    let mut missing = REQUIRED
+        .iter()
+        .filter(|field| !passport.contains_key(field))
+        .cloned()
+        .collect::<Vec<_>>();
+    missing.sort();
+    let mut invalid = vec![];
+    if check_values {
+        invalid = passport
+            .iter()
+            .filter_map(|(&key, value)| is_valid_entry(key, value).err().map(|e| (key, e)))
+            .collect();
+        invalid.sort_by_key(|(field, _)| *field);
+    }
+    Diagnosis {
+        unreadable: vec![],
+        missing,
+        invalid,
+    }
 }
 
 fn has_required_keys(passport: &Passport) -> bool {
-    let keys: HashSet<_> = passport.keys().cloned().collect();
-    !REQUIRED.difference(&keys).next().is_some()
+    diagnose(passport, false).is_ok()
 }
 
 fn is_valid(passport: &Passport) -> bool {
-    if !has_required_keys(passport) {
-        return false;
+    diagnose(passport, true).is_ok()
+}
+
+// keeps what can be read of a passport, diagnosing the rest rather than giving up
+fn diagnose_raw(raw_passport: &str, check_values: bool) -> Diagnosis {
+    let mut passport = Passport::new();
+    let mut unreadable = vec![];
+    for raw_entry in raw_passport.split_whitespace() {
+        match parse_entry(raw_entry) {
+            Ok((key, value)) => {
+                passport.insert(key, value);
+            }
+            Err(e) => unreadable.push((raw_entry.to_string(), e.to_string())),
+        }
     }
-    let keys: HashSet<_> = passport.keys().cloned().collect();
-    if REQUIRED.difference(&keys).next().is_some() {
-        return false;
+    Diagnosis {
+        unreadable,
+        ..diagnose(&passport, check_values)
     }
+}
 
-    for (&key, value) in passport.iter() {
-        if is_valid_entry(key, value).is_err() {
-            return false;
+// a line per passport, then how many were valid, how often each kind of problem came up and
+// how often each field was missing
+fn report(input: &str, check_values: bool) -> String {
+    let mut lines = vec![];
+    let mut valid = 0;
+    let mut counts = BTreeMap::new();
+    let mut missing = BTreeMap::new();
+    let passports = input.split("\n\n").collect::<Vec<_>>();
+    for (i, raw_passport) in passports.iter().enumerate() {
+        let diagnosis = diagnose_raw(raw_passport, check_values);
+        if diagnosis.is_ok() {
+            valid += 1;
         }
+        if !diagnosis.unreadable.is_empty() {
+            *counts.entry("unreadable entry").or_insert(0) += diagnosis.unreadable.len();
+        }
+        for &field in &diagnosis.missing {
+            *missing.entry(field).or_insert(0) += 1;
+        }
+        for (_, invalid) in &diagnosis.invalid {
+            *counts.entry(invalid.kind()).or_insert(0) += 1;
+        }
+        lines.push(format!("passport {}: {}", i + 1, diagnosis));
+    }
+    lines.push(format!("{} of {} valid", valid, passports.len()));
+    for (kind, count) in counts {
+        lines.push(format!("{}: {}", kind, count));
     }
+    for (field, count) in missing {
+        lines.push(format!("missing {}: {}", field, count));
+    }
+    lines.join("\n")
+}
 
-    true
+// the report in place of the answer when `--report` is given. part 1 only checks for missing
+// fields
+fn with_report(
+    input: &str,
+    options: &Options,
+    check_values: bool,
+    part: fn(&str) -> Result<usize>,
+) -> Result<Answer<usize>> {
+    if options.flag("report") {
+        return Ok(Answer::Text(report(input, check_values)));
+    }
+    part(input).map(Answer::Value)
 }
 
 fn part1(input: &str) -> Result<usize> {
@@ -276,4 +465,112 @@ iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719"
         );
         Ok(())
     }
+
+    #[test]
+    fn test_diagnose() -> Result<()> {
+        let passport = parse_passport("hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 byr:x")?;
+        let diagnosis = diagnose(&passport, true);
+        assert_eq!(diagnosis.missing, vec![Field::Pid]);
+        assert_eq!(
+            diagnosis.invalid,
+            vec![
+                (Field::Byr, Invalid::NotANumber("x".into())),
+                (
+                    Field::Iyr,
+                    Invalid::OutOfRange {
+                        value: 2023,
+                        min: 2010,
+                        max: 2020
+                    }
+                ),
+                (
+                    Field::Eyr,
+                    Invalid::OutOfRange {
+                        value: 2038,
+                        min: 2020,
+                        max: 2030
+                    }
+                ),
+                (
+                    Field::Hgt,
+                    Invalid::OutOfRange {
+                        value: 59,
+                        min: 150,
+                        max: 193
+                    }
+                ),
+                (Field::Hcl, Invalid::BadHairColour("74454a".into())),
+                (Field::Ecl, Invalid::BadEyeColour("zzz".into())),
+            ]
+        );
+        assert_eq!(diagnose(&passport, false).invalid, vec![]);
+        assert_eq!(
+            is_valid_entry(Field::Hgt, "1"),
+            Err(Invalid::BadUnit("1".into()))
+        );
+        // exactly as many digits as asked for, and nothing else
+        assert_eq!(is_valid_entry(Field::Pid, "000000000"), Ok(()));
+        for pid in &["+12345678", "-12345678", "12345678"] {
+            assert_eq!(
+                is_valid_entry(Field::Pid, pid),
+                Err(Invalid::BadPassportId(pid.to_string()))
+            );
+        }
+        assert_eq!(is_valid_entry(Field::Hcl, "#0a9FfE"), Ok(()));
+        for hcl in &["#+abcde", "#+abc", "#1234567", "#12345"] {
+            assert_eq!(
+                is_valid_entry(Field::Hcl, hcl),
+                Err(Invalid::BadHairColour(hcl.to_string()))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        let input = "eyr:1972 cid:100
+hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
+
+iyr:2019
+hcl:#602927 eyr:2027 hgt:170cm
+ecl:grn pid:012533040 byr:1946
+
+hcl:#602927 eyr:2027 hgt:170cm xyz:1 byr";
+        let options = Options::new().with("report", None);
+        assert_eq!(
+            with_report(input, &options, true, part2)?.to_string(),
+            "passport 1: eyr: out of range: 1972 isn't in 2020..=2030; \
+hgt: bad unit: `170` isn't in cm or in; \
+pid: malformed passport id: `186cm` isn't nine digits
+passport 2: valid
+passport 3: unreadable `xyz:1`: Invalid field `xyz`; unreadable `byr`: No value; \
+missing byr, iyr, ecl, pid
+1 of 3 valid
+bad unit: 1
+malformed passport id: 1
+out of range: 1
+unreadable entry: 2
+missing byr: 1
+missing iyr: 1
+missing ecl: 1
+missing pid: 1"
+        );
+        assert_eq!(
+            with_report(input, &options, false, part1)?.to_string(),
+            "passport 1: valid
+passport 2: valid
+passport 3: unreadable `xyz:1`: Invalid field `xyz`; unreadable `byr`: No value; \
+missing byr, iyr, ecl, pid
+2 of 3 valid
+unreadable entry: 2
+missing byr: 1
+missing iyr: 1
+missing ecl: 1
+missing pid: 1"
+        );
+        // without `--report`, bad entries are still errors
+        assert!(with_report(input, &Options::new(), true, part2).is_err());
+        assert_eq!(with_report(INPUT, &Options::new(), false, part1)?, 2);
+        Ok(())
+    }
 }
@@ -1,11 +1,46 @@
 use anyhow::{anyhow, bail, Result};
-use aoc2020::dispatch;
+use aoc2020::big_int::Integer;
+use aoc2020::dot::{self, Cluster, Edge, Graph, DOT};
+use aoc2020::shared::{dispatch_with, Answer, Opt, Options};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const TARGET: &str = "shiny gold";
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(
+        part1,
+        part2,
+        &[
+            Opt {
+                name: "bag",
+                help: "Ask about this bag instead of shiny gold",
+                value: Some("BAG"),
+            },
+            Opt {
+                name: "list",
+                help: "List the bags that hold the bag (part 1) or that it holds (part 2), with how many",
+                value: None,
+            },
+            Opt {
+                name: "outline",
+                help: "Show everything the bag holds as an indented tree",
+                value: None,
+            },
+            Opt {
+                name: "outline-compact",
+                help: "Like --outline, but only show what a bag holds the first time it comes up",
+                value: None,
+            },
+            Opt {
+                name: "explain",
+                help: "Show how part 2's total is built up from the bags inside",
+                value: None,
+            },
+            DOT,
+        ],
+    )
 }
 
 #[derive(Debug)]
@@ -61,63 +96,304 @@ fn parse(input: &str) -> Result<Vec<Rule>> {
     input.split('\n').map(parse_line).collect()
 }
 
-fn index<'a>(contains: &'a [Rule]) -> HashMap<&'a str, &'a Rule<'a>> {
-    let mut index = HashMap::new();
-    for rule in contains {
-        index.insert(rule.container, rule);
-    }
-    index
+// the rules as a graph, with an edge from each bag to each bag it directly contains
+struct BagGraph<'a> {
+    // every bag mentioned, with what it directly contains sorted by name
+    contents: HashMap<&'a str, Vec<(&'a str, usize)>>,
+    // every bag, after all the bags it contains
+    order: Vec<&'a str>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Visiting,
+    Done,
 }
 
-fn inverted_index<'a>(contains: &'a [Rule]) -> HashMap<&'a str, Vec<&'a str>> {
-    let mut index = HashMap::new();
-    for rule in contains {
-        for &bag in rule.contains.keys() {
-            index.entry(bag).or_insert(vec![]).push(rule.container);
+impl<'a> BagGraph<'a> {
+    fn new(rules: &[Rule<'a>]) -> Result<Self> {
+        let mut contents: HashMap<&str, Vec<(&str, usize)>> = HashMap::new();
+        for rule in rules {
+            let mut inner = rule
+                .contains
+                .iter()
+                .map(|(&bag, &count)| (bag, count))
+                .collect::<Vec<_>>();
+            inner.sort_unstable();
+            if contents.insert(rule.container, inner).is_some() {
+                bail!("more than one rule for {} bags", rule.container);
+            }
+        }
+        // bags without a rule of their own contain nothing
+        for rule in rules {
+            for &bag in rule.contains.keys() {
+                contents.entry(bag).or_default();
+            }
         }
+        let mut graph = BagGraph {
+            contents,
+            order: vec![],
+        };
+        graph.order = graph.topological_order()?;
+        Ok(graph)
     }
-    index
-}
 
-fn part1(input: &str) -> Result<usize> {
-    let bags = parse(input)?;
-    let index = inverted_index(&bags);
-    let mut queue: VecDeque<&str> = VecDeque::new();
-    let mut seen = HashSet::new();
-    queue.push_back("shiny gold".into());
-    while let Some(bag) = queue.pop_front() {
-        seen.insert(bag);
-        if let Some(containers) = index.get(&bag) {
-            for container in containers {
-                if !seen.contains(container) {
-                    queue.push_back(container);
+    // depth first, so a bag met again while its own contents are being visited is on a cycle.
+    // uses its own stack rather than recursing, so long chains of rules can't overflow
+    fn topological_order(&self) -> Result<Vec<&'a str>> {
+        let mut bags = self.contents.keys().copied().collect::<Vec<_>>();
+        bags.sort_unstable();
+        let mut marks = HashMap::new();
+        let mut order = vec![];
+        for bag in bags {
+            if marks.contains_key(bag) {
+                continue;
+            }
+            marks.insert(bag, Mark::Visiting);
+            // each entry is a bag being visited along with the contents still to visit, so
+            // the stack is also the path to the current bag
+            let mut stack = vec![(bag, self.contents(bag).iter())];
+            while let Some((outer, pending)) = stack.last_mut() {
+                match pending.next() {
+                    Some(&(inner, _)) => match marks.get(inner) {
+                        Some(Mark::Done) => {}
+                        Some(Mark::Visiting) => {
+                            let path = stack.iter().map(|&(b, _)| b).collect::<Vec<_>>();
+                            let start = path
+                                .iter()
+                                .position(|&b| b == inner)
+                                .expect("bags being visited are on the path");
+                            let mut cycle = path[start..].to_vec();
+                            cycle.push(inner);
+                            bail!("cyclic rules: {}", cycle.join(" -> "));
+                        }
+                        None => {
+                            marks.insert(inner, Mark::Visiting);
+                            stack.push((inner, self.contents(inner).iter()));
+                        }
+                    },
+                    None => {
+                        marks.insert(*outer, Mark::Done);
+                        order.push(*outer);
+                        stack.pop();
+                    }
                 }
             }
         }
+        Ok(order)
+    }
+
+    fn contents(&self, bag: &str) -> &[(&'a str, usize)] {
+        self.contents.get(bag).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    fn check(&self, bag: &str) -> Result<()> {
+        if !self.contents.contains_key(bag) {
+            bail!("no rule mentions {} bags", bag);
+        }
+        Ok(())
+    }
+
+    // every bag that eventually holds `bag`, with how many `bag`s one of them holds in all,
+    // sorted by name
+    fn ancestors(&self, bag: &str) -> Result<Vec<(&'a str, Integer)>> {
+        self.check(bag)?;
+        let mut copies: HashMap<&str, Integer> = HashMap::new();
+        // contents come first in the order, so their copies are known by the time a bag's are
+        for &outer in &self.order {
+            let count = if outer == bag {
+                Integer::from(1)
+            } else {
+                self.contents(outer)
+                    .iter()
+                    .filter_map(|&(inner, n)| Some(count(n) * copies.get(inner)?.clone()))
+                    .sum()
+            };
+            if count != 0 {
+                copies.insert(outer, count);
+            }
+        }
+        copies.remove(bag);
+        let mut res = copies.into_iter().collect::<Vec<_>>();
+        res.sort_unstable();
+        Ok(res)
+    }
+
+    // every bag eventually inside `bag`, with how many of it there are in all, sorted by name
+    fn descendants(&self, bag: &'a str) -> Result<Vec<(&'a str, Integer)>> {
+        self.check(bag)?;
+        let mut totals = HashMap::new();
+        totals.insert(bag, Integer::from(1));
+        // containers come first in reverse order, so a bag's total is known before its contents'
+        for &outer in self.order.iter().rev() {
+            if let Some(total) = totals.get(outer).cloned() {
+                for &(inner, n) in self.contents(outer) {
+                    *totals.entry(inner).or_insert_with(|| 0.into()) += total.clone() * count(n);
+                }
+            }
+        }
+        totals.remove(bag);
+        let mut res = totals.into_iter().collect::<Vec<_>>();
+        res.sort_unstable();
+        Ok(res)
+    }
+
+    // how many bags each bag holds in all. a bag comes after its contents in the order, so
+    // their totals are ready to reuse rather than counting each subtree again wherever it
+    // appears. totals too big for an i64 carry on as `BigInt`s
+    fn totals(&self) -> HashMap<&'a str, Integer> {
+        let mut totals: HashMap<&str, Integer> = HashMap::new();
+        for &bag in &self.order {
+            let total = self
+                .contents(bag)
+                .iter()
+                .map(|&(inner, n)| count(n) * (Integer::from(1) + totals[inner].clone()))
+                .sum();
+            totals.insert(bag, total);
+        }
+        totals
+    }
+
+    fn total(&self, bag: &str) -> Result<Integer> {
+        self.check(bag)?;
+        Ok(self.totals().remove(bag).expect("every bag has a total"))
+    }
+
+    // how the total for `bag` is built up from the totals of the bags it holds, one line per
+    // bag inside it, innermost first
+    fn explain(&self, bag: &'a str) -> Result<String> {
+        let totals = self.totals();
+        let mut inside = self
+            .descendants(bag)?
+            .into_iter()
+            .map(|(inner, _)| inner)
+            .collect::<HashSet<_>>();
+        inside.insert(bag);
+        let mut lines = vec![];
+        for &outer in self.order.iter().filter(|b| inside.contains(*b)) {
+            let terms = self
+                .contents(outer)
+                .iter()
+                .map(|(inner, n)| format!("{} * (1 + {})", n, totals[inner]))
+                .collect::<Vec<_>>();
+            lines.push(if terms.is_empty() {
+                format!("{}: 0", outer)
+            } else {
+                format!("{}: {} = {}", outer, terms.join(" + "), totals[outer])
+            });
+        }
+        Ok(lines.join("\n"))
+    }
+
+    // the containment tree under `bag`, indented two spaces a level, with each bag's count.
+    // with `compact`, a bag's contents are only shown the first time and later ones refer back
+    // to them, so bags held by many others don't make the outline blow up
+    fn outline(&self, bag: &'a str, compact: bool) -> Result<String> {
+        self.check(bag)?;
+        let mut lines = vec![bag.to_string()];
+        let mut shown = HashSet::new();
+        // the contents still to outline at each level, down to the current bag
+        let mut stack = vec![self.contents(bag).iter()];
+        while let Some(pending) = stack.last_mut() {
+            match pending.next() {
+                Some(&(inner, n)) => {
+                    let line = format!("{}{} {}", "  ".repeat(stack.len()), n, inner);
+                    if compact && !self.contents(inner).is_empty() && !shown.insert(inner) {
+                        lines.push(format!("{} (see above)", line));
+                    } else {
+                        lines.push(line);
+                        stack.push(self.contents(inner).iter());
+                    }
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        Ok(lines.join("\n"))
     }
-    Ok(seen.len() - 1)
-}
 
-fn part2(input: &str) -> Result<usize> {
-    let bags = parse(input)?;
-    let index = index(&bags);
-    let mut total = 0;
-    let mut queue: VecDeque<(usize, &str)> = VecDeque::new();
-    queue.push_back((1, "shiny gold".into()));
-    while let Some((count, bag)) = queue.pop_front() {
-        if let Some(rule) = index.get(bag) {
-            for (subbag, subcount) in rule.contains.iter() {
-                queue.push_back((count * subcount, subbag));
+    // every bag, boxed together with the others of its shade, with an edge to each bag it
+    // directly holds
+    fn dot(&self) -> Graph {
+        let mut bags = self.order.clone();
+        bags.sort_unstable();
+        let mut shades = BTreeMap::new();
+        for &bag in &bags {
+            let shade = bag.split(' ').next().expect("split always yields a part");
+            shades
+                .entry(shade)
+                .or_insert_with(|| Cluster::new(shade))
+                .add_node(bag, bag);
+        }
+        let mut graph = Graph::directed("bags");
+        for cluster in shades.into_values() {
+            graph.add_cluster(cluster);
+        }
+        for &bag in &bags {
+            for &(inner, n) in self.contents(bag) {
+                graph.add_edge(Edge::new(bag, inner).label(n).weight(n));
             }
         }
-        total += count;
+        graph
     }
-    Ok(total - 1)
+}
+
+fn count(n: usize) -> Integer {
+    Integer::from(n as i64)
+}
+
+fn list(bags: &[(&str, Integer)]) -> String {
+    bags.iter()
+        .map(|(bag, count)| format!("{}: {}", bag, count))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn part1(input: &str, options: &Options) -> Result<Answer<usize>> {
+    let rules = parse(input)?;
+    let graph = BagGraph::new(&rules)?;
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(graph.dot()));
+    }
+    let bag = options.value("bag").unwrap_or(TARGET);
+    if options.flag("outline") || options.flag("outline-compact") {
+        let compact = options.flag("outline-compact");
+        return Ok(Answer::Text(graph.outline(bag, compact)?));
+    }
+    let ancestors = graph.ancestors(bag)?;
+    Ok(if options.flag("list") {
+        Answer::Text(list(&ancestors))
+    } else {
+        Answer::Value(ancestors.len())
+    })
+}
+
+fn part2(input: &str, options: &Options) -> Result<Answer<Integer>> {
+    let rules = parse(input)?;
+    let graph = BagGraph::new(&rules)?;
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(graph.dot()));
+    }
+    let bag = options.value("bag").unwrap_or(TARGET);
+    if options.flag("outline") || options.flag("outline-compact") {
+        let compact = options.flag("outline-compact");
+        return Ok(Answer::Text(graph.outline(bag, compact)?));
+    }
+    if options.flag("explain") {
+        return Ok(Answer::Text(graph.explain(bag)?));
+    }
+    Ok(if options.flag("list") {
+        Answer::Text(list(&graph.descendants(bag)?))
+    } else {
+        Answer::Value(graph.total(bag)?)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc2020::big_int::BigInt;
 
     const INPUT: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
 dark orange bags contain 3 bright white bags, 4 muted yellow bags.
@@ -131,13 +407,13 @@ dotted black bags contain no other bags.";
 
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(part1(INPUT)?, 4);
+        assert_eq!(part1(INPUT, &Options::new())?, 4);
         Ok(())
     }
 
     #[test]
     fn test_part2() -> Result<()> {
-        assert_eq!(part2(INPUT)?, 32);
+        assert_eq!(part2(INPUT, &Options::new())?, Integer::from(32));
         Ok(())
     }
 
@@ -151,9 +427,184 @@ dark orange bags contain 2 dark yellow bags.
 dark yellow bags contain 2 dark green bags.
 dark green bags contain 2 dark blue bags.
 dark blue bags contain 2 dark violet bags.
-dark violet bags contain no other bags."
+dark violet bags contain no other bags.",
+                &Options::new()
             )?,
-            126
+            Integer::from(126)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_queries() -> Result<()> {
+        let rules = parse(INPUT)?;
+        let graph = BagGraph::new(&rules)?;
+        let counts = |counts: &[(&'static str, i64)]| {
+            counts
+                .iter()
+                .map(|&(bag, n)| (bag, Integer::from(n)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            graph.ancestors("shiny gold")?,
+            counts(&[
+                ("bright white", 1),
+                ("dark orange", 3 + 4 * 2),
+                ("light red", 1 + 2 * 2),
+                ("muted yellow", 2),
+            ])
+        );
+        assert_eq!(
+            graph.descendants("shiny gold")?,
+            counts(&[
+                ("dark olive", 1),
+                ("dotted black", 4 + 2 * 6),
+                ("faded blue", 3 + 2 * 5),
+                ("vibrant plum", 2),
+            ])
+        );
+        assert_eq!(graph.ancestors("light red")?, vec![]);
+        assert_eq!(graph.descendants("faded blue")?, vec![]);
+        assert!(graph.descendants("plaid purple").is_err());
+
+        let options = Options::new().with("bag", Some("faded blue"));
+        assert_eq!(part1(INPUT, &options)?, 7);
+        assert_eq!(part2(INPUT, &options)?, Integer::from(0));
+        let options = options.with("list", None);
+        assert_eq!(
+            part1(INPUT, &options.with("bag", Some("dark olive")))?.to_string(),
+            "bright white: 1\ndark orange: 11\nlight red: 5\nmuted yellow: 2\nshiny gold: 1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<()> {
+        let options = Options::new().with("explain", None);
+        assert_eq!(
+            part2(INPUT, &options)?.to_string(),
+            "dotted black: 0
+faded blue: 0
+dark olive: 4 * (1 + 0) + 3 * (1 + 0) = 7
+vibrant plum: 6 * (1 + 0) + 5 * (1 + 0) = 11
+shiny gold: 1 * (1 + 7) + 2 * (1 + 11) = 32"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_big_totals() -> Result<()> {
+        // 100 levels of 9 bags each, which would take forever without reusing totals and
+        // overflows any primitive integer
+        let mut rules = (0..100)
+            .flat_map(|i| {
+                vec![
+                    format!(
+                        "level a{} bags contain 4 level a{} bags, 5 level b{} bags.",
+                        i,
+                        i + 1,
+                        i + 1
+                    ),
+                    format!("level b{} bags contain 9 level a{} bags.", i, i + 1),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rules.push("level a100 bags contain no other bags.".into());
+        rules.push("level b100 bags contain no other bags.".into());
+        let input = rules.join("\n");
+        let options = Options::new().with("bag", Some("level a0"));
+        // both kinds of bag hold 9 bags a level, so (9^101 - 9) / 8 in all
+        let nines = (0..101).fold(BigInt::from(1), |acc, _| acc * BigInt::from(9));
+        let expected = (nines - BigInt::from(9)) / BigInt::from(8);
+        assert_eq!(part2(&input, &options)?, Integer::from(expected));
+        // each level's bags are only outlined once, so it takes a few lines a level rather than
+        // 2^100 of them
+        let outline = part2(&input, &options.with("outline-compact", None))?.to_string();
+        assert_eq!(outline.lines().count(), 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_outline() -> Result<()> {
+        let options = Options::new().with("outline", None);
+        assert_eq!(
+            part2(INPUT, &options)?.to_string(),
+            "shiny gold
+  1 dark olive
+    4 dotted black
+    3 faded blue
+  2 vibrant plum
+    6 dotted black
+    5 faded blue"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_outline_shared() -> Result<()> {
+        let input = "pale red bags contain 1 pale orange bag, 2 pale yellow bags.
+pale orange bags contain 3 pale green bags.
+pale yellow bags contain 4 pale green bags.
+pale green bags contain 5 pale blue bags.
+pale blue bags contain no other bags.";
+        let options = Options::new().with("bag", Some("pale red"));
+        assert_eq!(
+            part2(input, &options.clone().with("outline", None))?.to_string(),
+            "pale red
+  1 pale orange
+    3 pale green
+      5 pale blue
+  2 pale yellow
+    4 pale green
+      5 pale blue"
+        );
+        assert_eq!(
+            part2(input, &options.with("outline-compact", None))?.to_string(),
+            "pale red
+  1 pale orange
+    3 pale green
+      5 pale blue
+  2 pale yellow
+    4 pale green (see above)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot() -> Result<()> {
+        let dot = part1(INPUT, &Options::new().with("dot", None))?.to_string();
+        assert!(dot.starts_with("digraph \"bags\" {\n"));
+        assert!(dot
+            .contains("        label=\"shiny\";\n        \"shiny gold\" [label=\"shiny gold\"];"));
+        assert!(dot.contains("\"muted yellow\" -> \"faded blue\" [label=\"9\", weight=\"9\"];"));
+        assert_eq!(dot.matches(" -> ").count(), 13);
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_chain() -> Result<()> {
+        // far deeper than the default stack could recurse
+        let depth = 100_000;
+        let mut rules = (0..depth)
+            .map(|i| format!("level a{} bags contain 1 level a{} bag.", i, i + 1))
+            .collect::<Vec<_>>();
+        rules.push(format!("level a{} bags contain no other bags.", depth));
+        let input = rules.join("\n");
+        let options = Options::new().with("bag", Some("level a0"));
+        assert_eq!(part2(&input, &options)?, Integer::from(depth));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cycle() -> Result<()> {
+        let input = "shiny gold bags contain 1 dark red bag.
+dark red bags contain 2 dark orange bags, 1 faded blue bag.
+dark orange bags contain 1 shiny gold bag.
+faded blue bags contain no other bags.";
+        let err = part2(input, &Options::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cyclic rules: dark orange -> shiny gold -> dark red -> dark orange"
         );
         Ok(())
     }
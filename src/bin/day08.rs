@@ -1,102 +1,214 @@
-use anyhow::{anyhow, bail, Error, Result};
-use aoc2020::dispatch;
-use std::collections::HashSet;
-use std::convert::TryFrom;
-
-#[derive(Debug, Clone)]
-enum Instruction {
-    Acc(i64),
-    Jmp(i64),
-    Nop(i64),
+use anyhow::{bail, Result};
+use aoc2020::assembler::{assemble, normalise};
+use aoc2020::debugger::Debugger;
+use aoc2020::dot::{self, Edge, Graph, DOT};
+use aoc2020::search::bfs;
+use aoc2020::shared::{dispatch_with, Answer, Opt, Options};
+use aoc2020::vm::{run, Instruction, Program, RunResult, Vm};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+
+fn main() -> Result<()> {
+    dispatch_with(
+        part1,
+        part2,
+        &[
+            Opt {
+                name: "debug",
+                help: "Step through the program (repaired, for part 2) with commands from stdin, \
+                       so give the program as a file. `help` lists the commands",
+                value: None,
+            },
+            Opt {
+                name: "listing",
+                help: "Print the program with labelled jumps and dead code marked, before the \
+                       answer. For part 2, the repaired program and which line was swapped",
+                value: None,
+            },
+            Opt {
+                name: "brute-force",
+                help: "Repair the program by trying every swap in turn",
+                value: None,
+            },
+            DOT,
+        ],
+    )
 }
 
-impl TryFrom<&str> for Instruction {
-    type Error = Error;
+// plain input, or assembler source with labels
+fn parse(input: &str) -> Result<Program<Instruction>> {
+    assemble(input)
+}
 
-    fn try_from(s: &str) -> Result<Self> {
-        let n: i64 = s[4..].parse()?;
-        Ok(match &s[..3] {
-            "acc" => Instruction::Acc(n),
-            "jmp" => Instruction::Jmp(n),
-            "nop" => Instruction::Nop(n),
-            _ => bail!("Invalid instruction `{}`", s),
-        })
+// the control flow, with an edge to wherever each instruction goes next. with `swaps`, also
+// a dashed edge to where it would go if jmp and nop were swapped
+fn control_flow(program: &Program<Instruction>, swaps: bool) -> Graph {
+    let mut graph = Graph::directed("program");
+    let mut targets = BTreeSet::new();
+    for (pc, instruction) in program.iter().enumerate() {
+        graph.add_node(pc, format!("{}: {}", pc, instruction));
+        let taken = instruction.target(pc as i64);
+        let swapped = Some(swap(instruction))
+            .filter(|swapped| swapped != instruction)
+            .map(|swapped| swapped.target(pc as i64));
+        graph.add_edge(Edge::new(pc, taken));
+        targets.insert(taken);
+        if let (true, Some(swapped)) = (swaps, swapped) {
+            graph.add_edge(Edge::new(pc, swapped).label("swapped").style("dashed"));
+            targets.insert(swapped);
+        }
     }
+    // the places control can go that aren't instructions
+    for target in targets {
+        if target == program.len() as i64 {
+            graph.add_node(target, "end");
+        } else if target < 0 || target > program.len() as i64 {
+            graph.add_node(target, format!("{}: segfault", target));
+        }
+    }
+    graph
 }
 
-fn main() -> Result<()> {
-    dispatch(part1, part2)
+// answers with the accumulator the program was left with
+fn debug(program: &Program<Instruction>) -> Result<Answer<i64>> {
+    let mut debugger = Debugger::new(program);
+    let stdin = io::stdin();
+    debugger.repl(stdin.lock(), io::stdout())?;
+    Ok(Answer::Value(debugger.vm().state().acc()))
 }
 
-type Program = Vec<Instruction>;
+fn part1(input: &str, options: &Options) -> Result<Answer<i64>> {
+    let program = parse(input)?;
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(control_flow(&program, false)));
+    }
+    if options.flag("listing") {
+        print!("{}", normalise(&program));
+    }
+    if options.flag("debug") {
+        return debug(&program);
+    }
+    match run(&program) {
+        (RunResult::Looped { .. }, state) => Ok(Answer::Value(state.acc())),
+        (result, _) => bail!("Unexpectedly stops without looping: {:?}", result),
+    }
+}
 
-fn parse(input: &str) -> Result<Program> {
-    input
-        .split("\n")
-        .map(|l| Instruction::try_from(l))
-        .collect::<Result<_>>()
+fn swap(instruction: &Instruction) -> Instruction {
+    match instruction {
+        Instruction::Nop(n) => Instruction::Jmp(*n),
+        Instruction::Jmp(n) => Instruction::Nop(*n),
+        Instruction::Acc(n) => Instruction::Acc(*n),
+    }
 }
 
 #[derive(Debug, PartialEq)]
-enum RunResult {
-    Loops(i64),
-    Terminates(i64),
+struct Repair {
+    // the index of the swapped instruction
+    pc: usize,
+    program: Program<Instruction>,
+    acc: i64,
 }
 
-fn run(program: &Program) -> Result<RunResult> {
-    let mut pc: i64 = 0;
-    let mut acc = 0;
-    let mut seen = HashSet::new();
-    while !seen.contains(&pc) {
-        if pc == program.len() as i64 {
-            return Ok(RunResult::Terminates(acc));
-        }
-        seen.insert(pc);
-        if pc < 0 {
-            bail!("segfault");
-        }
-        let instruction: &Instruction = program.get(pc as usize).ok_or(anyhow!("segfault"))?;
-        match instruction {
-            Instruction::Acc(n) => {
-                acc += n;
-                pc += 1
-            }
-            Instruction::Jmp(n) => pc += n,
-            Instruction::Nop(_) => pc += 1,
+impl Repair {
+    fn new(program: Program<Instruction>, pc: usize) -> Result<Self> {
+        match run(&program) {
+            (RunResult::Terminated { .. }, state) => Ok(Repair {
+                pc,
+                program,
+                acc: state.acc(),
+            }),
+            (result, _) => bail!("swapping line {} doesn't work: {:?}", pc + 1, result),
         }
     }
-    Ok(RunResult::Loops(acc))
 }
 
-fn part1(input: &str) -> Result<i64> {
-    let program = parse(input)?;
-    if let RunResult::Loops(acc) = run(&program)? {
-        Ok(acc)
-    } else {
-        bail!("Unexpectedly terminates")
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let swapped = &self.program[self.pc];
+        write!(
+            f,
+            "swapped line {} from `{}` to `{}`, leaving acc at {}",
+            self.pc + 1,
+            swap(swapped),
+            swapped,
+            self.acc
+        )
     }
 }
 
-fn swap(instruction: &Instruction) -> Instruction {
-    match instruction {
-        Instruction::Nop(n) => Instruction::Jmp(*n),
-        Instruction::Jmp(n) => Instruction::Nop(*n),
-        Instruction::Acc(n) => Instruction::Acc(*n),
+// finds the swap in linear time. the pcs that lead to the end are found by walking the control
+// flow backwards from just past the last instruction. only a swap on the looping path can
+// change where the program goes, and it works when it sends the pc into that set. the rest of
+// the way can't pass the swapped instruction again, since then the original would have reached
+// the end too
+fn repair(program: Program<Instruction>) -> Result<Repair> {
+    if program.is_empty() {
+        bail!("the program is empty");
+    }
+    let end = program.len();
+    let mut predecessors = vec![vec![]; end + 1];
+    for (pc, instruction) in program.iter().enumerate() {
+        let next = instruction.target(pc as i64);
+        if (0..=end as i64).contains(&next) {
+            predecessors[next as usize].push(pc);
+        }
     }
+    let reaches_end = bfs(end, |&pc| predecessors[pc].clone());
+
+    let mut vm = Vm::new(&program);
+    let fixed = loop {
+        let pc = vm.state().pc as usize;
+        let swapped = swap(&program[pc]);
+        let next = swapped.target(pc as i64);
+        if swapped != program[pc] && next >= 0 && reaches_end.contains(&(next as usize)) {
+            break pc;
+        }
+        match vm.step() {
+            None => {}
+            Some(RunResult::Looped { .. }) => bail!("no terminating solution found"),
+            Some(RunResult::Terminated { .. }) => bail!("the program already terminates"),
+            Some(result) => bail!("the program fails before looping: {:?}", result),
+        }
+    };
+    let mut program = program;
+    program[fixed] = swap(&program[fixed]);
+    Repair::new(program, fixed)
 }
 
-fn part2(input: &str) -> Result<i64> {
-    let mut program = parse(input)?;
+// tries swapping each instruction in turn, which takes quadratic time
+fn repair_brute_force(mut program: Program<Instruction>) -> Result<Repair> {
     for index in 0..program.len() {
         program[index] = swap(&program[index]);
-        if let RunResult::Terminates(val) = run(&program)? {
-            return Ok(val);
+        if let (RunResult::Terminated { .. }, _) = run(&program) {
+            return Repair::new(program, index);
         }
         program[index] = swap(&program[index]);
     }
     bail!("no terminating solution found");
 }
 
+fn part2(input: &str, options: &Options) -> Result<Answer<i64>> {
+    let program = parse(input)?;
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(control_flow(&program, true)));
+    }
+    let repaired = if options.flag("brute-force") {
+        repair_brute_force(program)?
+    } else {
+        repair(program)?
+    };
+    if options.flag("listing") {
+        // as a comment, so the listing still assembles
+        print!("; {}\n{}", repaired, normalise(&repaired.program));
+    }
+    if options.flag("debug") {
+        return debug(&repaired.program);
+    }
+    Ok(Answer::Value(repaired.acc))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,14 +225,16 @@ acc +6";
 
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(part1(INPUT)?, 5);
+        assert_eq!(part1(INPUT, &Options::new())?, 5);
         Ok(())
     }
 
     #[test]
     fn test_loops() -> Result<()> {
         let program = parse(INPUT)?;
-        assert_eq!(run(&program)?, RunResult::Loops(5));
+        let (result, state) = run(&program);
+        assert_eq!(result, RunResult::Looped { pc: 1, steps: 7 });
+        assert_eq!(state.acc(), 5);
         Ok(())
     }
 
@@ -137,13 +251,133 @@ acc +1
 nop -4
 acc +6",
         )?;
-        assert_eq!(run(&program)?, RunResult::Terminates(8));
+        let (result, state) = run(&program);
+        assert_eq!(result, RunResult::Terminated { steps: 6 });
+        assert_eq!(state.acc(), 8);
         Ok(())
     }
 
     #[test]
     fn test_part2() -> Result<()> {
-        assert_eq!(part2(INPUT)?, 8);
+        assert_eq!(part2(INPUT, &Options::new())?, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair() -> Result<()> {
+        let repaired = repair(parse(INPUT)?)?;
+        assert_eq!(repaired.pc, 7);
+        assert_eq!(repaired.acc, 8);
+        assert_eq!(
+            repaired.to_string(),
+            "swapped line 8 from `jmp -4` to `nop -4`, leaving acc at 8"
+        );
+        assert_eq!(repaired, repair_brute_force(parse(INPUT)?)?);
+        assert_eq!(part2(INPUT, &Options::new().with("brute-force", None))?, 8);
+        assert!(repair(parse("acc +1\nnop +0")?).is_err());
+        assert!(repair(parse("")?).is_err());
+        assert!(repair_brute_force(parse("")?).is_err());
+        // swapping the only jmp leads into another loop
+        assert!(repair(parse("acc +1\njmp -1\nacc +2\njmp -3")?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair_matches_brute_force() -> Result<()> {
+        // small pseudo-random programs, checked against every swap that works
+        let mut seed: u64 = 12345;
+        let mut random = |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        let mut looping = 0;
+        for _ in 0..500 {
+            let len = 1 + random(12) as i64;
+            let instructions = (0..len)
+                .map(|_| {
+                    let n = random(2 * len as u64 + 1) as i64 - len;
+                    match random(3) {
+                        0 => Instruction::Acc(n),
+                        1 => Instruction::Jmp(n),
+                        _ => Instruction::Nop(n),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let program = Program::from(instructions);
+            if !matches!(run(&program).0, RunResult::Looped { .. }) {
+                continue;
+            }
+            looping += 1;
+            let working = (0..program.len())
+                .filter_map(|pc| {
+                    let mut swapped = program.clone();
+                    swapped[pc] = swap(&swapped[pc]);
+                    Repair::new(swapped, pc).ok()
+                })
+                .collect::<Vec<_>>();
+            match repair(program.clone()) {
+                Ok(repaired) => assert!(working.contains(&repaired), "{:?}", program),
+                Err(_) => assert!(working.is_empty(), "{:?}", program),
+            }
+            assert_eq!(
+                repair_brute_force(program).ok().map(|r| r.pc),
+                working.first().map(|r| r.pc)
+            );
+        }
+        assert!(looping > 100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_labels() -> Result<()> {
+        let source = "    nop +0
+start:
+    acc +1
+    jmp skip
+back:
+    acc +3
+    jmp start       ; loops forever, unless swapped
+    acc -99
+skip:
+    acc +1
+    jmp back
+    acc +6";
+        assert_eq!(parse(source)?, parse(INPUT)?);
+        assert_eq!(part1(source, &Options::new())?, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_part2_out_of_bounds() -> Result<()> {
+        // swapping the first instruction jumps out of the program, which just rules it out
+        assert_eq!(part2("nop +5\njmp -1\nacc +3", &Options::new())?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot() -> Result<()> {
+        let options = Options::new().with("dot", None);
+        let dot = part1("nop +0\njmp -1\nacc +3\njmp +2", &options)?.to_string();
+        assert_eq!(
+            dot,
+            r#"digraph "program" {
+    "0" [label="0: nop +0"];
+    "1" [label="1: jmp -1"];
+    "2" [label="2: acc +3"];
+    "3" [label="3: jmp +2"];
+    "5" [label="5: segfault"];
+    "0" -> "1";
+    "1" -> "0";
+    "2" -> "3";
+    "3" -> "5";
+}"#
+        );
+        let dot = part2("nop +0\njmp -1\nacc +3\njmp +2", &options)?.to_string();
+        assert!(dot.contains("\"4\" [label=\"end\"];"));
+        assert!(dot.contains("\"0\" -> \"0\" [label=\"swapped\", style=\"dashed\"];"));
+        assert!(dot.contains("\"1\" -> \"2\" [label=\"swapped\", style=\"dashed\"];"));
         Ok(())
     }
 }
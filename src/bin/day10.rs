@@ -1,4 +1,5 @@
 use anyhow::{anyhow, bail, Result};
+use aoc2020::big_int::Integer;
 use aoc2020::dispatch;
 
 fn main() -> Result<()> {
@@ -33,13 +34,14 @@ fn part1(input: &str) -> Result<i32> {
     Ok(ones * threes)
 }
 
-fn part2(input: &str) -> Result<i64> {
+// the count grows exponentially with the number of adapters, so it can outgrow an i64
+fn part2(input: &str) -> Result<Integer> {
     let mut numbers = parse(input)?;
     numbers.push(0);
     numbers.sort();
 
-    let mut counts = vec![1];
-    let mut count = 0;
+    let mut counts = vec![Integer::from(1)];
+    let mut count = Integer::from(0);
 
     for (index, number) in numbers.iter().enumerate().skip(1) {
         count =
@@ -49,9 +51,9 @@ fn part2(input: &str) -> Result<i64> {
             // filter out any that differ by more than 3
             .filter(|&prev_idx| number - numbers[prev_idx] <= 3)
             // sum their respective counts
-            .map(|prev_idx| counts[prev_idx])
+            .map(|prev_idx| counts[prev_idx].clone())
             .sum();
-        counts.push(count);
+        counts.push(count.clone());
     }
     Ok(count)
 }
@@ -127,4 +129,19 @@ mod tests {
         assert_eq!(part2(INPUT2)?, 19208);
         Ok(())
     }
+
+    #[test]
+    fn test_part2_overflow() -> Result<()> {
+        // with every step 1, the counts are the tribonacci numbers
+        let input = (1..=100)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut tribonacci: [u128; 3] = [0, 0, 1];
+        for _ in 0..100 {
+            tribonacci = [tribonacci[1], tribonacci[2], tribonacci.iter().sum()];
+        }
+        assert_eq!(part2(&input)?.to_string(), tribonacci[2].to_string());
+        Ok(())
+    }
 }
@@ -1,13 +1,15 @@
 use anyhow::{bail, Error, Result};
-use aoc2020::coor::Coor;
-use aoc2020::dispatch;
+use aoc2020::automaton::{Cell, GridAutomaton, LifeRule, Neighbourhood};
+use aoc2020::grid::{Connectivity, Grid};
+use aoc2020::shared::{dispatch_with, Options};
+use aoc2020::visualise::{Palette, Visualiser, VISUALISE};
 use std::convert::TryFrom;
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(part1, part2, &[VISUALISE])
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 enum Tile {
     Floor,
     Empty,
@@ -21,139 +23,87 @@ impl TryFrom<char> for Tile {
         Ok(match c {
             '.' => Tile::Floor,
             'L' => Tile::Empty,
+            '#' => Tile::Occupied,
             _ => bail!("Invalid tile `{}`", c),
         })
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-enum NeighbourType {
-    Adjacent,
-    LoS,
-}
-
-struct Map {
-    tiles: Vec<Tile>,
-    width: usize,
-}
-
-impl Map {
-    fn from_str(input: &str) -> Result<Self> {
-        let width = input.find('\n').unwrap_or(input.len());
-        let tiles = input
-            .chars()
-            .filter(|&c| c != '\n')
-            .map(|c| Tile::try_from(c))
-            .collect::<Result<Vec<_>>>()?;
-        Ok(Map { tiles, width })
+impl From<Tile> for char {
+    fn from(tile: Tile) -> char {
+        match tile {
+            Tile::Floor => '.',
+            Tile::Empty => 'L',
+            Tile::Occupied => '#',
+        }
     }
+}
 
-    fn height(&self) -> usize {
-        self.tiles.len() / self.width
+impl Cell for Tile {
+    fn is_live(&self) -> bool {
+        *self == Tile::Occupied
     }
 
-    fn idx(&self, coor: &Coor) -> Option<usize> {
-        if coor.y < 0
-            || coor.y >= (self.height() as i64)
-            || coor.x < 0
-            || coor.x >= (self.width as i64)
-        {
-            return None;
+    fn with_live(&self, live: bool) -> Self {
+        if live {
+            Tile::Occupied
+        } else {
+            Tile::Empty
         }
-        Some((coor.y as usize) * self.width + (coor.x as usize))
     }
 
-    fn coor(&self, idx: usize) -> Coor {
-        Coor::new((idx % self.width) as i64, (idx / self.width) as i64)
+    fn is_static(&self) -> bool {
+        *self == Tile::Floor
     }
+}
 
-    fn get(&self, coor: &Coor) -> Option<Tile> {
-        let idx = self.idx(coor)?;
-        self.tiles.get(idx).map(|&t| t)
-    }
+// empty seats without occupied neighbours fill up, occupied seats with `min_neighbours` or
+// more occupied neighbours empty
+fn seating(
+    input: &str,
+    neighbourhood: Neighbourhood,
+    min_neighbours: usize,
+) -> Result<GridAutomaton<Tile, LifeRule>> {
+    let survival = (0..min_neighbours).collect::<Vec<_>>();
+    let rule = LifeRule::new(&[0], &survival);
+    Ok(GridAutomaton::new(Grid::parse(input)?, rule, neighbourhood))
+}
 
-    fn occupied_neighbours(&self, coor: &Coor, nt: NeighbourType) -> usize {
-        [
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            (0, -1),
-            (0, 1),
-            (1, -1),
-            (1, 0),
-            (1, 1),
-        ]
-        .iter()
-        .map(|&(dx, dy)| Coor::new(dx, dy))
-        .map(|direction| {
-            let mut occupied = 0;
-            let mut pos = *coor + direction;
-            while let Some(tile) = self.get(&pos) {
-                if tile == Tile::Empty {
-                    break;
-                }
-                if tile == Tile::Occupied {
-                    occupied += 1;
-                    break;
-                }
-                match nt {
-                    NeighbourType::Adjacent => break,
-                    NeighbourType::LoS => pos += direction,
-                }
-            }
-            occupied
-        })
-        .sum()
-    }
+fn palette() -> Palette {
+    Palette::new(&[
+        ('.', [40, 40, 40]),
+        ('L', [60, 180, 75]),
+        ('#', [230, 25, 75]),
+    ])
+}
 
-    fn next_tiles(&self, nt: NeighbourType, min_neighbours: usize) -> Option<Vec<Tile>> {
-        let mut changed = false;
-        let tiles = self
-            .tiles
-            .iter()
-            .enumerate()
-            .map(|(idx, tile)| (tile, self.occupied_neighbours(&self.coor(idx), nt)))
-            .map(|(&tile, n)| match (tile, n) {
-                (Tile::Empty, 0) => {
-                    changed = true;
-                    Tile::Occupied
-                }
-                (Tile::Occupied, m) if m >= min_neighbours => {
-                    changed = true;
-                    Tile::Empty
-                }
-                (tile, _) => tile,
-            })
-            .collect::<Vec<_>>();
-
-        if changed {
-            Some(tiles)
-        } else {
-            None
+fn settle(mut seats: GridAutomaton<Tile, LifeRule>, options: &Options) -> Result<usize> {
+    let history = seats.find_cycle(None);
+    if let Some(mut visualiser) = Visualiser::from_options(options, palette())? {
+        for grid in history.states() {
+            visualiser.frame(&grid.to_string())?;
         }
     }
-
-    fn run(&mut self, nt: NeighbourType, min_neighbours: usize) -> usize {
-        while let Some(tiles) = self.next_tiles(nt, min_neighbours) {
-            self.tiles = tiles;
-        }
-        self.tiles.iter().filter(|&t| *t == Tile::Occupied).count()
+    match history.cycle() {
+        Some(cycle) if cycle.is_fixed_point() => Ok(seats.live()),
+        _ => bail!("seating never settles"),
     }
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let mut map = Map::from_str(input)?;
-    Ok(map.run(NeighbourType::Adjacent, 4))
+fn part1(input: &str, options: &Options) -> Result<usize> {
+    let seats = seating(input, Neighbourhood::Adjacent(Connectivity::Eight), 4)?;
+    settle(seats, options)
 }
 
-fn part2(input: &str) -> Result<usize> {
-    let mut map = Map::from_str(input)?;
-    Ok(map.run(NeighbourType::LoS, 5))
+fn part2(input: &str, options: &Options) -> Result<usize> {
+    let seats = seating(input, Neighbourhood::LineOfSight(Connectivity::Eight), 5)?;
+    settle(seats, options)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc2020::coor::Coor;
 
     const INPUT: &str = "L.LL.LL.LL
 LLLLLLL.LL
@@ -168,37 +118,69 @@ L.LLLLL.LL";
 
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(part1(INPUT)?, 37);
+        assert_eq!(part1(INPUT, &Options::new())?, 37);
         Ok(())
     }
 
     #[test]
     fn test_part2() -> Result<()> {
-        assert_eq!(part2(INPUT)?, 26);
+        assert_eq!(part2(INPUT, &Options::new())?, 26);
         Ok(())
     }
 
     #[test]
     fn test_coor() -> Result<()> {
-        let map = Map::from_str(
+        let tiles: Grid<Tile> = Grid::parse(
             "...
 ...",
         )?;
         for idx in 0..6 {
-            assert_eq!(map.idx(&map.coor(idx)), Some(idx));
+            assert_eq!(tiles.idx(&tiles.coor(idx)), Some(idx));
         }
         Ok(())
     }
 
     #[test]
     fn test_lookup() -> Result<()> {
-        let map = Map::from_str(
+        let tiles: Grid<Tile> = Grid::parse(
             ".L.
 ...",
         )?;
-        assert_eq!(map.get(&Coor::new(1, 0)), Some(Tile::Empty));
-        assert_eq!(map.get(&Coor::new(3, 0)), None);
-        assert_eq!(map.get(&Coor::new(0, 4)), None);
+        assert_eq!(tiles.get(&Coor::new(1, 0)), Some(&Tile::Empty));
+        assert_eq!(tiles.get(&Coor::new(3, 0)), None);
+        assert_eq!(tiles.get(&Coor::new(0, 4)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_visualise() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("aoc2020-day11-{}", std::process::id()));
+        let target = format!("text:{}", dir.display());
+        part1(INPUT, &Options::new().with("visualise", Some(&target)))?;
+        // the initial layout and every change until it settles
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 6);
+        assert_eq!(std::fs::read_to_string(dir.join("frame0000.txt"))?, INPUT);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() -> Result<()> {
+        let mut seats = seating(INPUT, Neighbourhood::Adjacent(Connectivity::Eight), 4)?;
+        assert_eq!(seats.run_until_stable(), 5);
+        assert_eq!(
+            seats.grid.to_string(),
+            "#.#L.L#.##
+#LLL#LL.L#
+L.#.L..#..
+#L##.##.L#
+#.#L.LL.LL
+#.#L#L#.##
+..L.L.....
+#L#L##L#L#
+#.LLLLLL.L
+#.#L#L#.##"
+        );
         Ok(())
     }
 }
@@ -1,9 +1,18 @@
 use anyhow::{anyhow, Result};
-use aoc2020::dispatch;
-use aoc2020::mod_arith::mod_div;
+use aoc2020::big_int::Integer;
+use aoc2020::mod_arith::{try_crt, try_crt_big, CrtError};
+use aoc2020::shared::{dispatch_with, Opt, Options};
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(
+        part1,
+        part2,
+        &[Opt {
+            name: "after",
+            help: "Find the first matching timestamp from this one on",
+            value: Some("T"),
+        }],
+    )
 }
 
 fn parse(input: &str) -> Result<(i64, Vec<i64>)> {
@@ -30,7 +39,7 @@ fn parse2(input: &str) -> Result<Vec<(i64, i64)>> {
         .collect())
 }
 
-fn part1(input: &str) -> Result<i64> {
+fn part1(input: &str, _options: &Options) -> Result<i64> {
     let (earliest, bus_times) = parse(input)?;
     let prod = bus_times
         .iter()
@@ -41,37 +50,35 @@ fn part1(input: &str) -> Result<i64> {
     Ok(prod)
 }
 
-// n == c1 mod n1
-// n == c2 mod n2
-// -> n == reduce.0 mod reduce.1
-fn reduce(c1: i64, n1: i64, c2: i64, n2: i64) -> (i64, i64) {
-    /*
-    x = c1 mod n1
-    x = c2 mod n2
-
-    x = k n1 + c1
-    k n1 + c1 = c2 mod n2
-    k n1 = c2 - c1 mod n2
-    k = (c2 - c1)/n1 mod n2
-    k = n2 t + (c2-c1)/n1
-
-    x = n1 n2 t +  ((c2-c2)/n1)*n1 + c1
-
-    */
-    let k = mod_div(c2 - c1, n1, n2);
-    let c = k * n1 + c1;
-    let n = n1 * n2;
+fn crt_error(e: CrtError) -> anyhow::Error {
+    match e {
+        CrtError::Inconsistent => anyhow!("the buses never line up"),
+        CrtError::Overflow => anyhow!("the buses' combined period overflows"),
+        CrtError::NonPositiveModulus => anyhow!("bus periods must be positive"),
+    }
+}
 
-    // add n (mod n) to keep c positive
-    ((c + n) % n, n)
+// the first timestamp from `after` on where bus i leaves i minutes after the first
+fn first_departure(input: &str, after: Integer) -> Result<Integer> {
+    let congruences = parse2(input)?;
+    let (x, period) = match try_crt(&congruences) {
+        Ok((x, period)) => (Integer::from(x), Integer::from(period)),
+        // the combined period doesn't fit in an i64, so start again without a limit
+        Err(CrtError::Overflow) => {
+            let (x, period) = try_crt_big(&congruences).map_err(crt_error)?;
+            (x.into(), period.into())
+        }
+        Err(e) => return Err(crt_error(e)),
+    };
+    if x >= after {
+        return Ok(x);
+    }
+    let periods = (after - x.clone() + period.clone() - Integer::from(1)) / period.clone();
+    Ok(x + periods * period)
 }
 
-fn part2(input: &str) -> Result<i64> {
-    let numbers = parse2(input)?;
-    let red = numbers[1..]
-        .iter()
-        .fold(numbers[0], |acc, el| reduce(acc.0, acc.1, el.0, el.1));
-    Ok(red.0)
+fn part2(input: &str, options: &Options) -> Result<Integer> {
+    first_departure(input, options.parse("after")?.unwrap_or_else(|| 0.into()))
 }
 
 #[cfg(test)]
@@ -83,23 +90,60 @@ mod tests {
 
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(part1(INPUT)?, 295);
+        assert_eq!(part1(INPUT, &Options::new())?, 295);
         Ok(())
     }
 
     #[test]
-    fn test_reduce() {
-        assert_eq!(reduce(-2, 13, -3, 19), (206, 247));
-        assert_eq!(reduce(-2, 13, 0, 17), (102, 221));
+    fn test_part2() -> Result<()> {
+        let options = Options::new();
+        assert_eq!(part2("\n17,x,13,19", &options)?, 3417);
+        assert_eq!(part2("\n67,7,59,61", &options)?, 754018);
+        assert_eq!(part2("\n67,x,7,59,61", &options)?, 779210);
+        assert_eq!(part2("\n67,7,x,59,61", &options)?, 1261476);
+        assert_eq!(part2("\n1789,37,47,1889", &options)?, 1202161486);
+        assert_eq!(part2(INPUT, &options)?, 1068781);
+        // non-coprime periods can still line up
+        assert_eq!(part2("\n6,x,4", &options)?, 6);
+        assert!(part2("\n6,4", &options).is_err());
+        Ok(())
     }
 
     #[test]
-    fn test_part2() -> Result<()> {
-        assert_eq!(part2("\n17,x,13,19")?, 3417);
-        assert_eq!(part2("\n67,7,59,61")?, 754018);
-        assert_eq!(part2("\n67,x,7,59,61")?, 779210);
-        assert_eq!(part2("\n67,7,x,59,61")?, 1261476);
-        assert_eq!(part2("\n1789,37,47,1889")?, 1202161486);
+    fn test_after() -> Result<()> {
+        let period = 7 * 13 * 59 * 31 * 19;
+        assert_eq!(first_departure(INPUT, 1068781.into())?, 1068781);
+        assert_eq!(first_departure(INPUT, 1068782.into())?, 1068781 + period);
+        let options = Options::new().with("after", Some("100000000000000"));
+        let first = part2(INPUT, &options)?.to_i64().expect("fits in an i64");
+        assert!(first >= 100000000000000 && first - period < 100000000000000);
+        assert_eq!(first % period, 1068781);
+        Ok(())
+    }
+
+    #[test]
+    fn test_big_periods() -> Result<()> {
+        // the product of the periods is around 10^24
+        let periods = [1000003, 1000033, 1000037, 1000039];
+        let input = format!(
+            "\n{},{},{},{}",
+            periods[0], periods[1], periods[2], periods[3]
+        );
+        let first = part2(&input, &Options::new())?;
+        assert!(first.to_i64().is_none());
+        for (i, &p) in periods.iter().enumerate() {
+            let offset = Integer::from(i as i64);
+            assert_eq!((first.clone() + offset) % Integer::from(p), 0);
+        }
+        let total: Integer = periods.iter().map(|&p| Integer::from(p)).product();
+        assert!(first < total);
+
+        // and `--after` past the first still finds the next one
+        let options = Options::new().with("after", Some("1000000000000000000000000000"));
+        let later = part2(&input, &options)?;
+        assert_eq!((later.clone() - first) % total.clone(), 0);
+        assert!(later >= "1000000000000000000000000000".parse()?);
+        assert!(later - total < "1000000000000000000000000000".parse()?);
         Ok(())
     }
 }
@@ -1,223 +1,190 @@
-use anyhow::Result;
-use aoc2020::dispatch;
-use std::collections::HashSet;
-use std::ops::Add;
+use anyhow::{bail, Result};
+use aoc2020::automaton::{Backend, InfiniteAutomaton, LifeRule, MirroredAutomaton};
+use aoc2020::coor::CoorN;
+use aoc2020::shared::{dispatch_with, Opt, Options};
+use aoc2020::sparse_grid::SparseGrid;
+use aoc2020::visualise::{Palette, Visualiser, VISUALISE};
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(
+        part1,
+        part2,
+        &[
+            Opt {
+                name: "backend",
+                help: "How to store the cubes: `sparse` (default) or `dense`",
+                value: Some("BACKEND"),
+            },
+            Opt {
+                name: "dims",
+                help: "Simulate this many dimensions instead of 3 or 4",
+                value: Some("N"),
+            },
+            Opt {
+                name: "mirror",
+                help: "Only simulate cubes with non-negative extra dimensions, by symmetry",
+                value: None,
+            },
+            VISUALISE,
+        ],
+    )
 }
 
-trait Coor: Copy {
-    fn from_index(idx: usize, size: usize) -> Self;
-
-    fn apply_deltas(f: &mut dyn FnMut(Self));
-}
-
-#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Hash)]
-struct Coor3 {
-    x: i64,
-    y: i64,
-    z: i64,
-}
-
-impl Coor3 {
-    const fn new(x: i64, y: i64, z: i64) -> Self {
-        Coor3 { x, y, z }
-    }
-}
-
-impl Add for Coor3 {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Coor3::new(self.x + other.x, self.y + other.y, self.z + other.z)
-    }
+const CYCLES: usize = 6;
+
+// the initial 2D slice, embedded in N dimensions
+fn parse<const N: usize>(input: &str) -> SparseGrid<CoorN<N>> {
+    let size = input.find('\n').unwrap_or(input.len());
+    input
+        .chars()
+        .filter(|&c| c != '\n')
+        .enumerate()
+        .filter(|(_, c)| *c == '#')
+        .map(|(i, _)| {
+            let mut coor = CoorN::default();
+            coor.0[0] = (i % size) as i64;
+            coor.0[1] = (i / size) as i64;
+            coor
+        })
+        .collect()
 }
-impl Coor for Coor3 {
-    fn from_index(idx: usize, size: usize) -> Coor3 {
-        let z = idx / size / size;
-        let y = (idx - z * size * size) / size;
-        let x = idx - z * size * size - y * size;
-        Coor3::new(x as i64, y as i64, z as i64)
-    }
 
-    fn apply_deltas(f: &mut dyn FnMut(Coor3)) {
-        for x in -1..=1 {
-            for y in -1..=1 {
-                for z in -1..=1 {
-                    f(Coor3::new(x, y as i64, z as i64))
-                }
-            }
+// only works out the cubes when visualising
+fn show<const N: usize, F>(visualiser: &mut Option<Visualiser>, cubes: F) -> Result<()>
+where
+    F: FnOnce() -> SparseGrid<CoorN<N>>,
+{
+    match visualiser {
+        Some(visualiser) => {
+            let labelled = visualiser.shows_text();
+            let text = cubes().render_slices(|c| if c.is_some() { '#' } else { '.' }, labelled);
+            visualiser.frame(&text)
         }
+        None => Ok(()),
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Hash)]
-struct Coor4 {
-    x: i64,
-    y: i64,
-    z: i64,
-    w: i64,
-}
-
-impl Coor4 {
-    const fn new(x: i64, y: i64, z: i64, w: i64) -> Self {
-        Coor4 { x, y, z, w }
-    }
-}
-
-impl Add for Coor4 {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        Coor4::new(
-            self.x + other.x,
-            self.y + other.y,
-            self.z + other.z,
-            self.w + other.w,
-        )
-    }
-}
-
-impl Coor for Coor4 {
-    fn from_index(idx: usize, size: usize) -> Coor4 {
-        let z = idx / size / size / size;
-        let y = (idx - z * size * size * size) / size / size;
-        let x = (idx - z * size * size * size - y * size * size) / size;
-        let w = idx - z * size * size * size - y * size * size - x * size;
-        Coor4::new(w as i64, x as i64, y as i64, z as i64)
-    }
-
-    fn apply_deltas(f: &mut dyn FnMut(Coor4)) {
-        for x in -1..=1 {
-            for y in -1..=1 {
-                for z in -1..=1 {
-                    for w in -1..=1 {
-                        f(Coor4::new(x, y as i64, z as i64, w as i64))
-                    }
-                }
-            }
+fn simulate<const N: usize>(input: &str, options: &Options) -> Result<usize> {
+    let palette = Palette::new(&[('#', [255, 255, 255]), ('.', [30, 30, 60])]);
+    let mut visualiser = Visualiser::from_options(options, palette)?;
+    let active = parse::<N>(input);
+    let rule: LifeRule = "B3/S23".parse()?;
+    let backend: Option<Backend> = options.parse("backend")?;
+    if options.flag("mirror") {
+        if backend.is_some() {
+            bail!("`--mirror` can't be combined with `--backend`");
         }
-    }
-}
-
-#[derive(Debug)]
-struct Space<T>
-where
-    T: Coor,
-{
-    active: HashSet<T>,
-    size: usize,
-}
-
-impl<T: Coor + Add<Output = T> + Default + Eq + std::hash::Hash> Space<T> {
-    fn from_str(input: &str) -> Result<Self> {
-        let size = input.find('\n').unwrap_or(input.len());
-        let active = input
-            .chars()
-            .filter(|&c| c != '\n')
-            .enumerate()
-            .filter(|(_, c)| *c == '#')
-            .map(|(i, _)| T::from_index(i, size))
-            .collect::<HashSet<_>>();
-        Ok(Space { active, size })
-    }
-
-    fn occupied_neighbours(&self, coor: &T) -> usize {
-        let mut count = 0;
-        T::apply_deltas(&mut |delta| {
-            if delta == T::default() {
-                return;
-            }
-            if self.active.contains(&(*coor + delta)) {
-                count += 1;
-            }
-        });
-        count
-    }
-
-    fn next_tiles(&self) -> HashSet<T> {
-        let mut to_consider = HashSet::new();
-        for &coor in self.active.iter() {
-            T::apply_deltas(&mut |delta| {
-                to_consider.insert(coor + delta);
-            });
+        // the slice starts out flat, so every state is symmetric in the extra dimensions
+        let mut cubes = MirroredAutomaton::new(active, 2, rule)?;
+        show(&mut visualiser, || cubes.live())?;
+        for _ in 0..CYCLES {
+            cubes.step();
+            show(&mut visualiser, || cubes.live())?;
         }
-        let mut next = HashSet::new();
-        for coor in to_consider {
-            let is_active = self.active.contains(&coor);
-            let new_state = match (is_active, self.occupied_neighbours(&coor)) {
-                (true, n) if n == 2 || n == 3 => true,
-                (false, n) if n == 3 => true,
-                _ => false,
-            };
-            if new_state {
-                next.insert(coor);
-            }
+        Ok(cubes.population())
+    } else {
+        let mut cubes = InfiniteAutomaton::new(backend.unwrap_or_default(), active, rule)?;
+        show(&mut visualiser, || cubes.live())?;
+        for _ in 0..CYCLES {
+            cubes.step();
+            show(&mut visualiser, || cubes.live())?;
         }
-        next
+        Ok(cubes.population())
     }
+}
 
-    fn run(&mut self, times: usize) -> usize {
-        for _ in 0..times {
-            self.active = self.next_tiles();
-        }
-        self.active.iter().count()
+fn run(input: &str, dims: usize, options: &Options) -> Result<usize> {
+    match options.parse("dims")?.unwrap_or(dims) {
+        2 => simulate::<2>(input, options),
+        3 => simulate::<3>(input, options),
+        4 => simulate::<4>(input, options),
+        5 => simulate::<5>(input, options),
+        6 => simulate::<6>(input, options),
+        7 => simulate::<7>(input, options),
+        8 => simulate::<8>(input, options),
+        dims => bail!("can't simulate {} dimensions", dims),
     }
 }
 
-fn part1(input: &str) -> Result<usize> {
-    let mut space: Space<Coor3> = Space::from_str(input)?;
-    Ok(space.run(6))
+fn part1(input: &str, options: &Options) -> Result<usize> {
+    run(input, 3, options)
 }
 
-fn part2(input: &str) -> Result<usize> {
-    let mut space: Space<Coor4> = Space::from_str(input)?;
-    Ok(space.run(6))
+fn part2(input: &str, options: &Options) -> Result<usize> {
+    run(input, 4, options)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const INPUT: &str = ".#.
+..#
+###";
+
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(
-            part1(
-                ".#.
-..#
-###"
-            )?,
-            112
-        );
+        assert_eq!(part1(INPUT, &Options::new())?, 112);
         Ok(())
     }
 
     #[test]
     fn test_part2() -> Result<()> {
-        assert_eq!(
-            part2(
-                ".#.
-..#
-###"
-            )?,
-            848
-        );
+        assert_eq!(part2(INPUT, &Options::new())?, 848);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dense() -> Result<()> {
+        let options = Options::new().with("backend", Some("dense"));
+        assert_eq!(part1(INPUT, &options)?, 112);
+        assert_eq!(part2(INPUT, &options)?, 848);
+        let options = Options::new().with("backend", Some("bits"));
+        assert!(part1(INPUT, &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mirror() -> Result<()> {
+        let mirror = Options::new().with("mirror", None);
+        assert_eq!(part1(INPUT, &mirror)?, 112);
+        assert_eq!(part2(INPUT, &mirror)?, 848);
+        // cross-check against simulating every cube
+        for dims in 2..=5 {
+            let dims = dims.to_string();
+            let full = Options::new().with("dims", Some(&dims));
+            let mirrored = full.clone().with("mirror", None);
+            assert_eq!(part1(INPUT, &mirrored)?, part1(INPUT, &full)?);
+        }
+        assert!(part1(INPUT, &mirror.with("backend", Some("dense"))).is_err());
+        assert!(part1(INPUT, &Options::new().with("dims", Some("1"))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_visualise() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("aoc2020-day17-{}", std::process::id()));
+        let target = format!("pgm:{}", dir.display());
+        let options = Options::new()
+            .with("visualise", Some(&target))
+            .with("mirror", None);
+        assert_eq!(part2(INPUT, &options)?, 848);
+        assert_eq!(std::fs::read_dir(&dir)?.count(), CYCLES + 1);
+        // the one 3x3 slice, without a `z=0, w=0` label row
+        let first = std::fs::read(dir.join("frame0000.pgm"))?;
+        assert!(first.starts_with(b"P5\n12 12\n"));
+        std::fs::remove_dir_all(dir)?;
         Ok(())
     }
 
     #[test]
     fn test_lookup() -> Result<()> {
-        let space: Space<Coor3> = Space::from_str(
+        let cubes = parse::<3>(
             "...
 ...
 ..#",
-        )?;
-        assert_eq!(space.active.len(), 1);
-        assert_eq!(
-            space.active,
-            [Coor3::new(2, 2, 0)].iter().cloned().collect()
         );
+        assert_eq!(cubes, [CoorN::new([2, 2, 0])].iter().cloned().collect());
         Ok(())
     }
 }
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use aoc2020::big_int::Integer;
 use aoc2020::dispatch;
 use pest::iterators::{Pair, Pairs};
 use pest::prec_climber::{Assoc, Operator, PrecClimber};
@@ -13,15 +14,16 @@ fn main() -> Result<()> {
 #[grammar = "bin/day18.pest"] // relative to src
 struct Calc;
 
-fn eval(expression: Pairs<Rule>, climber: &PrecClimber<Rule>) -> i64 {
+// big products overflow into a `BigInt` rather than wrapping
+fn eval(expression: Pairs<Rule>, climber: &PrecClimber<Rule>) -> Integer {
     climber.climb(
         expression,
         |pair: Pair<Rule>| match pair.as_rule() {
-            Rule::num => pair.as_str().parse::<i64>().unwrap(),
+            Rule::num => pair.as_str().parse::<Integer>().unwrap(),
             Rule::expr => eval(pair.into_inner(), climber),
             _ => unreachable!(),
         },
-        |lhs: i64, op: Pair<Rule>, rhs: i64| match op.as_rule() {
+        |lhs: Integer, op: Pair<Rule>, rhs: Integer| match op.as_rule() {
             Rule::add => lhs + rhs,
             Rule::multiply => lhs * rhs,
             _ => unreachable!(),
@@ -29,22 +31,20 @@ fn eval(expression: Pairs<Rule>, climber: &PrecClimber<Rule>) -> i64 {
     )
 }
 
-fn part1(input: &str) -> Result<i64> {
+fn part1(input: &str) -> Result<Integer> {
     use Assoc::*;
     use Rule::*;
     let climber = PrecClimber::new(vec![
         Operator::new(add, Left) | Operator::new(multiply, Left),
     ]);
 
-    let mut sum = 0;
-    for line in input.split('\n') {
-        let ast = Calc::parse(Rule::calculation, line)?;
-        sum += eval(ast, &climber);
-    }
-    Ok(sum)
+    input
+        .split('\n')
+        .map(|line| Ok(eval(Calc::parse(Rule::calculation, line)?, &climber)))
+        .sum()
 }
 
-fn part2(input: &str) -> Result<i64> {
+fn part2(input: &str) -> Result<Integer> {
     use Assoc::*;
     use Rule::*;
     let climber = PrecClimber::new(vec![
@@ -52,12 +52,10 @@ fn part2(input: &str) -> Result<i64> {
         Operator::new(add, Left),
     ]);
 
-    let mut sum = 0;
-    for line in input.split('\n') {
-        let ast = Calc::parse(Rule::calculation, line)?;
-        sum += eval(ast, &climber);
-    }
-    Ok(sum)
+    input
+        .split('\n')
+        .map(|line| Ok(eval(Calc::parse(Rule::calculation, line)?, &climber)))
+        .sum()
 }
 
 #[cfg(test)]
@@ -76,4 +74,15 @@ mod tests {
         assert_eq!(part2("1 + 2 * 3 + 4 * 5 + 6")?, 231);
         Ok(())
     }
+
+    #[test]
+    fn test_overflow() -> Result<()> {
+        let input = "999999999999 * 999999999999 * 999999999999\n1";
+        assert_eq!(
+            part1(input)?.to_string(),
+            "999999999997000000000003000000000000"
+        );
+        assert_eq!(part2("(4611686018427387904 + 4611686018427387904) * 0")?, 0);
+        Ok(())
+    }
 }
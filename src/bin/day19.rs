@@ -1,11 +1,12 @@
 use anyhow::{anyhow, bail, Error, Result};
-use aoc2020::dispatch;
+use aoc2020::dot::{self, Cluster, Edge, Graph, DOT};
+use aoc2020::shared::{dispatch_with, Answer, Options};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(part1, part2, &[DOT])
 }
 
 #[derive(Debug, PartialEq)]
@@ -65,14 +66,17 @@ impl<'a> TryFrom<&'a str> for RuleValue<'a> {
     }
 }
 
+fn parse_rules(input: &str) -> Result<Vec<Rule<'_>>> {
+    let raw_rules = input
+        .split("\n\n")
+        .next()
+        .expect("split always yields a part");
+    raw_rules.split('\n').map(Rule::try_from).collect()
+}
+
 fn parse<'a>(input: &'a str) -> Result<(HashMap<usize, String>, Vec<&'a str>)> {
-    let mut parts = input.split("\n\n");
-    let raw_rules = parts.next().unwrap();
-    let raw_messages = parts.next().unwrap();
-    let rules = raw_rules
-        .split('\n')
-        .map(Rule::try_from)
-        .collect::<Result<Vec<_>>>()?;
+    let rules = parse_rules(input)?;
+    let raw_messages = input.split("\n\n").nth(1).unwrap();
     let messages = raw_messages.split('\n').collect::<Vec<_>>();
 
     let rule_map: HashMap<usize, &Rule> = rules.iter().map(|r| (r.number, r)).collect();
@@ -122,16 +126,67 @@ fn parse<'a>(input: &'a str) -> Result<(HashMap<usize, String>, Vec<&'a str>)> {
     Ok((patterns, messages))
 }
 
-fn part1(input: &str) -> Result<usize> {
+// the rules that loop in part 2
+const LOOPING_RULES: [&str; 2] = ["8: 42 | 42 8", "11: 42 31 | 42 11 31"];
+
+// an edge from each rule to each rule it refers to, labelled with its position: which
+// alternative and where in it, when there's more than one alternative. the rules for single
+// letters are boxed together
+fn rule_graph(rules: &[Rule]) -> Graph {
+    let mut graph = Graph::directed("rules");
+    let mut letters = Cluster::new("letters");
+    for rule in rules {
+        match &rule.value {
+            RuleValue::Literal(lit) => {
+                letters.add_node(rule.number, format!("{}: {}", rule.number, lit))
+            }
+            RuleValue::Combination(entries) => {
+                graph.add_node(rule.number, rule.number);
+                let alternatives = entries.split(|e| *e == RuleEntry::Or).collect::<Vec<_>>();
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    for (j, entry) in alternative.iter().enumerate() {
+                        if let RuleEntry::Number(n) = entry {
+                            let position = if alternatives.len() > 1 {
+                                format!("{}.{}", i + 1, j + 1)
+                            } else {
+                                (j + 1).to_string()
+                            };
+                            graph.add_edge(Edge::new(rule.number, n).label(position));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    graph.add_cluster(letters);
+    graph
+}
+
+fn part1(input: &str, options: &Options) -> Result<Answer<usize>> {
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(rule_graph(&parse_rules(input)?)));
+    }
     let (patterns, messages) = parse(input)?;
 
     let pattern = patterns.get(&0).expect("have all patterns now");
 
     let re = Regex::new(&format!("^{}$", pattern)).expect("invalid regex");
-    Ok(messages.iter().filter(|m| re.is_match(m)).count())
+    Ok(Answer::Value(
+        messages.iter().filter(|m| re.is_match(m)).count(),
+    ))
 }
 
-fn part2(input: &str) -> Result<usize> {
+fn part2(input: &str, options: &Options) -> Result<Answer<usize>> {
+    if dot::wanted(options) {
+        let looping = LOOPING_RULES
+            .iter()
+            .map(|&r| Rule::try_from(r))
+            .collect::<Result<Vec<_>>>()?;
+        let mut rules = parse_rules(input)?;
+        rules.retain(|rule| looping.iter().all(|l| l.number != rule.number));
+        rules.extend(looping);
+        return Ok(Answer::Graph(rule_graph(&rules)));
+    }
     let (patterns, messages) = parse(input)?;
     let p42 = &patterns.get(&42).expect("have 42");
     let p31 = &patterns.get(&31).expect("have 31");
@@ -142,10 +197,12 @@ fn part2(input: &str) -> Result<usize> {
                 .expect("invalid regex")
         })
         .collect::<Vec<_>>();
-    Ok(messages
-        .iter()
-        .filter(|m| repeated_re.iter().any(|re| re.is_match(m)))
-        .count())
+    Ok(Answer::Value(
+        messages
+            .iter()
+            .filter(|m| repeated_re.iter().any(|re| re.is_match(m)))
+            .count(),
+    ))
 }
 
 #[cfg(test)]
@@ -167,7 +224,7 @@ aaaabbb"#;
 
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(part1(INPUT)?, 2);
+        assert_eq!(part1(INPUT, &Options::new())?, 2);
         Ok(())
     }
 
@@ -221,7 +278,8 @@ aaaaabbaabaaaaababaa
 aaaabbaaaabbaaa
 aaaabbaabbaaaaaaabbbabbbaaabbaabaaa
 babaaabbbaaabaababbaabababaaab
-aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"#
+aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"#,
+                &Options::new()
             )?,
             12
         );
@@ -268,4 +326,19 @@ aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"#
         );
         Ok(())
     }
+
+    #[test]
+    fn test_dot() -> Result<()> {
+        let options = Options::new().with("dot", None);
+        let dot = part1(INPUT, &options)?.to_string();
+        assert!(dot.contains("\"0\" -> \"5\" [label=\"3\"];"));
+        assert!(dot.contains("\"1\" -> \"3\" [label=\"2.1\"];"));
+        assert!(dot.contains("        label=\"letters\";\n        \"4\" [label=\"4: a\"];"));
+
+        let dot = part2("8: 1\n11: 1 1\n1: \"a\"\n\na", &options)?.to_string();
+        assert!(dot.contains("\"8\" -> \"8\" [label=\"2.2\"];"));
+        assert!(dot.contains("\"11\" -> \"31\" [label=\"2.3\"];"));
+        assert!(!dot.contains("\"8\" -> \"1\""));
+        Ok(())
+    }
 }
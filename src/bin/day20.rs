@@ -1,10 +1,42 @@
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Error, Result};
 use aoc2020::coor::Coor;
-use aoc2020::dispatch;
-use std::collections::{HashSet, VecDeque};
+use aoc2020::dot::{self, Cluster, Graph, DOT};
+use aoc2020::grid::Grid;
+use aoc2020::pattern::Pattern;
+use aoc2020::search::dfs;
+use aoc2020::shared::{dispatch_with, Answer, Opt, Options};
+use aoc2020::symmetry::{Edge, D4, EDGES};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fs;
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(
+        part1,
+        part2,
+        &[
+            Opt {
+                name: "monster",
+                help: "Look for the shape in this file instead of the usual sea monster, with \
+                       spaces for cells that can be anything",
+                value: Some("PATH"),
+            },
+            DOT,
+        ],
+    )
+}
+
+// the shape looked for when `--monster` isn't given
+const MONSTER: &str = include_str!("day20.monster");
+
+fn monster(options: &Options) -> Result<Pattern<Pixel>> {
+    let shape = match options.value("monster") {
+        Some(path) => {
+            fs::read_to_string(path).map_err(|e| anyhow!("can't read monster `{}`: {}", path, e))?
+        }
+        None => MONSTER.to_string(),
+    };
+    Pattern::parse(&shape, ' ')
 }
 
 struct Map {
@@ -26,17 +58,15 @@ impl Map {
         Coor::new(x as i64, y as i64)
     }
 
-    fn neighbours(&self, index: usize, debug: bool) -> Vec<(usize, Edge)> {
-        if debug {
-            dbg!(self.size);
-        }
+    // neighbouring grid positions, with the edge of the neighbour facing `index`
+    fn neighbours(&self, index: usize) -> Vec<(usize, Edge)> {
         let mut res = vec![];
         let coor = self.from_index(index);
         for (offset, edge) in &[
             (Coor::new(-1, 0), Edge::Right),
             (Coor::new(1, 0), Edge::Left),
-            (Coor::new(0, -1), Edge::Down),
-            (Coor::new(0, 1), Edge::Up),
+            (Coor::new(0, -1), Edge::Bottom),
+            (Coor::new(0, 1), Edge::Top),
         ] {
             if let Some(index) = self.to_index(*offset + coor) {
                 res.push((index, *edge));
@@ -46,188 +76,68 @@ impl Map {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Edge {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Edge {
-    fn facing(&self) -> Edge {
-        use Edge::*;
-        match self {
-            Up => Down,
-            Down => Up,
-            Left => Right,
-            Right => Left,
-        }
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq)]
-struct DirectedEdge {
-    edge: Edge,
-    reversed: bool,
+enum Pixel {
+    Off,
+    On,
 }
 
-impl DirectedEdge {
-    fn new(edge: Edge, reversed: bool) -> Self {
-        DirectedEdge { edge, reversed }
+impl TryFrom<char> for Pixel {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self> {
+        Ok(match c {
+            '.' => Pixel::Off,
+            '#' => Pixel::On,
+            _ => bail!("invalid tile `{}`", c),
+        })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Orientation {
-    mirrored: bool,
-    top: Edge,
+impl From<Pixel> for char {
+    fn from(pixel: Pixel) -> char {
+        match pixel {
+            Pixel::Off => '.',
+            Pixel::On => '#',
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 struct Tile {
     number: usize,
-    size: usize,
-    data: Vec<Vec<bool>>,
-}
-
-fn as_char(el: &&bool) -> char {
-    match **el {
-        false => '.',
-        true => '#',
-    }
+    data: Grid<Pixel>,
 }
 
-fn edge_to_num<'a, I>(edge: I, debug: bool) -> usize
+fn edge_to_num<I>(edge: I) -> usize
 where
-    I: Iterator<Item = &'a bool>,
+    I: Iterator<Item = bool>,
 {
-    let foo: Vec<_> = edge.collect();
-    if debug {
-        println!("edge: {}", foo.iter().map(as_char).collect::<String>());
-    }
-    let edge = foo.iter();
-    edge.fold(0, |acc, &el| (acc << 1) + *el as usize)
+    edge.fold(0, |acc, el| (acc << 1) + el as usize)
 }
 
 impl Tile {
     fn from_str(input: &str) -> Result<Self> {
-        let mut lines = input.split('\n').peekable();
+        let mut lines = input.splitn(2, '\n');
         let title = lines.next().ok_or(anyhow!("title missing"))?;
         let number = title["Tile ".len()..title.len() - 1].parse()?;
-        let size = lines.peek().ok_or(anyhow!("grid missing"))?.len();
-        let data = lines
-            .filter(|l| l.len() > 0)
-            .map(|l| {
-                l.chars()
-                    .map(|c| {
-                        Ok(match c {
-                            '.' => false,
-                            '#' => true,
-                            _ => bail!("invalid tile `{}`", c),
-                        })
-                    })
-                    .collect::<Result<Vec<_>>>()
-            })
-            .collect::<Result<Vec<_>>>()?;
-        Ok(Tile { number, size, data })
-    }
-
-    fn base_edge_hash(&self, edge: DirectedEdge, debug: bool) -> usize {
-        match (edge.edge, edge.reversed) {
-            (Edge::Up, false) => edge_to_num(self.data[0].iter(), debug),
-            (Edge::Down, false) => edge_to_num(self.data[self.size - 1].iter(), debug),
-            (Edge::Left, false) => edge_to_num(self.data.iter().map(|r| &r[0]), debug),
-            (Edge::Right, false) => edge_to_num(self.data.iter().map(|r| &r[self.size - 1]), debug),
-
-            (Edge::Up, true) => edge_to_num(self.data[0].iter().rev(), debug),
-            (Edge::Down, true) => edge_to_num(self.data[self.size - 1].iter().rev(), debug),
-            (Edge::Left, true) => edge_to_num(self.data.iter().rev().map(|r| &r[0]), debug),
-            (Edge::Right, true) => {
-                edge_to_num(self.data.iter().rev().map(|r| &r[self.size - 1]), debug)
-            }
-        }
+        let data = Grid::parse(lines.next().ok_or(anyhow!("grid missing"))?)?;
+        Ok(Tile { number, data })
     }
 
-    fn image_row(&self, edge: DirectedEdge, row: usize) -> Vec<&bool> {
-        match (edge.edge, edge.reversed) {
-            (Edge::Up, false) => self.data[row].iter().collect(),
-            (Edge::Down, false) => self.data[self.size - 1 - row].iter().collect(),
-            (Edge::Left, false) => self.data.iter().map(|r| &r[row]).collect(),
-            (Edge::Right, false) => self.data.iter().map(|r| &r[self.size - 1 - row]).collect(),
-
-            (Edge::Up, true) => self.data[row].iter().rev().collect(),
-            (Edge::Down, true) => self.data[self.size - 1 - row].iter().rev().collect(),
-            (Edge::Left, true) => self.data.iter().rev().map(|r| &r[row]).collect(),
-            (Edge::Right, true) => self
-                .data
-                .iter()
-                .rev()
-                .map(|r| &r[self.size - 1 - row])
-                .collect(),
-        }
-    }
-
-    fn as_base_edge(&self, orientation: Orientation, edge: Edge) -> DirectedEdge {
-        let mut base = if !orientation.mirrored {
-            /*
-              U1 U2
-              L1 R1
-              L2 R2
-              D1 D2
-            */
-            [
-                DirectedEdge::new(Edge::Up, false),
-                DirectedEdge::new(Edge::Right, false),
-                DirectedEdge::new(Edge::Down, false),
-                DirectedEdge::new(Edge::Left, false),
-            ]
-        } else {
-            /*
-              U2 U1
-              R1 L1
-              R2 L2
-              D2 D1
-            */
-            [
-                DirectedEdge::new(Edge::Up, true),
-                DirectedEdge::new(Edge::Left, false),
-                DirectedEdge::new(Edge::Down, true),
-                DirectedEdge::new(Edge::Right, false),
-            ]
-        };
-        while base[0].edge != orientation.top {
-            base.rotate_right(1);
-            base[0].reversed = !base[0].reversed;
-            base[2].reversed = !base[2].reversed;
-        }
-        let index = match edge {
-            Edge::Up => 0,
-            Edge::Right => 1,
-            Edge::Down => 2,
-            Edge::Left => 3,
-        };
-        base[index]
-    }
-
-    fn edge_hash(&self, orientation: Orientation, edge: Edge, debug: bool) -> usize {
-        let base = self.as_base_edge(orientation, edge);
-        self.base_edge_hash(base, debug)
+    fn size(&self) -> usize {
+        self.data.width()
     }
 
-    fn _print(&self) {
-        for row in &self.data {
-            for c in row {
-                print!(
-                    "{}",
-                    match c {
-                        false => '.',
-                        true => '#',
-                    }
-                );
-            }
-            print!("\n");
+    // the edge of the tile after transforming it by `orientation`, as read in the transformed
+    // tile. looked up on the untransformed data to avoid copying the tile
+    fn edge_hash(&self, orientation: D4, edge: Edge) -> usize {
+        let (base, reversed) = orientation.inverse().apply_edge(edge);
+        let mut pixels = self.data.edge(base);
+        if reversed {
+            pixels.reverse();
         }
+        edge_to_num(pixels.into_iter().map(|p| p == Pixel::On))
     }
 }
 
@@ -238,203 +148,151 @@ fn parse(input: &str) -> Result<Vec<Tile>> {
         .collect::<Result<Vec<_>>>()
 }
 
-fn find_grid(input: &str) -> Result<Vec<(Tile, Orientation)>> {
-    let tiles = parse(input)?;
-    let size = (tiles.len() as f32).sqrt() as usize;
-    let map = Map { size };
-    let mut queue = VecDeque::new();
-    for tile_idx in 0..tiles.len() {
-        for &mirrored in &[false, true] {
-            for &top in &[Edge::Up, Edge::Right, Edge::Down, Edge::Left] {
-                queue.push_back(vec![(tile_idx, Orientation { top, mirrored })]);
+// tiles joined when they have an edge in common, labelled with which of their edges as given.
+// corners match two others, the rest of the border three and the interior four
+fn adjacency(tiles: &[Tile]) -> Graph {
+    // each edge read both ways, since either tile may need flipping
+    let hashes = |tile: &Tile, edge: Edge| {
+        let mut pixels = tile.data.edge(edge);
+        let forward = edge_to_num(pixels.iter().map(|&p| p == Pixel::On));
+        pixels.reverse();
+        let backward = edge_to_num(pixels.iter().map(|&p| p == Pixel::On));
+        [forward, backward]
+    };
+    let mut graph = Graph::undirected("tiles");
+    let mut matches = vec![0; tiles.len()];
+    for (i, a) in tiles.iter().enumerate() {
+        for (j, b) in tiles.iter().enumerate().skip(i + 1) {
+            let shared = EDGES.iter().find_map(|&edge_a| {
+                let forward = hashes(a, edge_a)[0];
+                EDGES
+                    .iter()
+                    .find(|&&edge_b| hashes(b, edge_b).contains(&forward))
+                    .map(|&edge_b| (edge_a, edge_b))
+            });
+            if let Some((edge_a, edge_b)) = shared {
+                let label = format!("{:?}-{:?}", edge_a, edge_b);
+                graph.add_edge(dot::Edge::new(a.number, b.number).label(label));
+                matches[i] += 1;
+                matches[j] += 1;
             }
         }
     }
-
-    while let Some(grid) = queue.pop_front() {
-        if grid.len() == tiles.len() && grid[0].1.mirrored {
-            return Ok(grid
-                .iter()
-                .map(|(tile_idx, orientation)| (tiles[*tile_idx].clone(), *orientation))
-                .collect::<Vec<_>>());
+    let mut clusters = [
+        Cluster::new("corners"),
+        Cluster::new("border"),
+        Cluster::new("interior"),
+    ];
+    for (tile, &count) in tiles.iter().zip(&matches) {
+        let label = format!("Tile {}", tile.number);
+        match count {
+            2..=4 => clusters[count - 2].add_node(tile.number, label),
+            _ => graph.add_node(tile.number, label),
         }
+    }
+    for cluster in clusters {
+        graph.add_cluster(cluster);
+    }
+    graph
+}
+
+fn find_grid(input: &str) -> Result<Vec<(Tile, D4)>> {
+    let tiles = parse(input)?;
+    let size = (tiles.len() as f32).sqrt() as usize;
+    let map = Map { size };
+
+    // fill in the grid row by row, each step placing any unused tile in any orientation
+    // that fits with the tiles already placed above and to the left
+    let successors = |grid: &Vec<(usize, D4)>| {
         let used = grid.iter().map(|t| t.0).collect::<HashSet<_>>();
+        let mut res = vec![];
         for next_tile_idx in 0..tiles.len() {
             if used.contains(&next_tile_idx) {
                 continue;
             }
             let next_tile = &tiles[next_tile_idx];
-            for &mirrored in &[false, true] {
-                for &top in &[Edge::Up, Edge::Right, Edge::Down, Edge::Left] {
-                    let debug = false;
-                    let next_orientation = Orientation { top, mirrored };
-                    let mut all_neighbours_ok = true;
-                    for (grid_neighbour_idx, neighbour_edge) in map.neighbours(grid.len(), false) {
-                        if let Some((tiles_neighbour_idx, neighbour_orientation)) =
-                            grid.get(grid_neighbour_idx)
-                        {
-                            let neighbour = &tiles[*tiles_neighbour_idx];
-                            if debug {
-                                dbg!(
-                                    grid_neighbour_idx,
-                                    tiles_neighbour_idx,
-                                    &neighbour.number,
-                                    neighbour_orientation,
-                                    neighbour_edge
-                                );
-                            }
-                            let neighbour_edge_hash =
-                                neighbour.edge_hash(*neighbour_orientation, neighbour_edge, debug);
-                            if debug {
-                                dbg!(&next_tile.number);
-                                dbg!(next_orientation);
-                                dbg!(neighbour_edge.facing());
-                            }
-                            let next_edge_hash = next_tile.edge_hash(
-                                next_orientation,
-                                neighbour_edge.facing(),
-                                debug,
-                            );
-                            if neighbour_edge_hash != next_edge_hash {
-                                all_neighbours_ok = false;
-                            }
+            for next_orientation in D4::all() {
+                let mut all_neighbours_ok = true;
+                for (grid_neighbour_idx, neighbour_edge) in map.neighbours(grid.len()) {
+                    if let Some((tiles_neighbour_idx, neighbour_orientation)) =
+                        grid.get(grid_neighbour_idx)
+                    {
+                        let neighbour = &tiles[*tiles_neighbour_idx];
+                        let neighbour_edge_hash =
+                            neighbour.edge_hash(*neighbour_orientation, neighbour_edge);
+                        let next_edge_hash =
+                            next_tile.edge_hash(next_orientation, neighbour_edge.facing());
+                        if neighbour_edge_hash != next_edge_hash {
+                            all_neighbours_ok = false;
                         }
                     }
-                    if debug {
-                        dbg!(all_neighbours_ok);
-                    }
-                    if !all_neighbours_ok {
-                        continue;
-                    }
-
-                    let mut next_grid = grid.clone();
-                    next_grid.push((next_tile_idx, next_orientation));
-                    queue.push_back(next_grid)
                 }
+                if !all_neighbours_ok {
+                    continue;
+                }
+
+                let mut next_grid = grid.clone();
+                next_grid.push((next_tile_idx, next_orientation));
+                res.push(next_grid)
             }
         }
-    }
-    bail!("failed to assemble grid");
-}
-
-fn part1(input: &str) -> Result<usize> {
-    let grid = find_grid(input)?;
-    let size = (grid.len() as f32).sqrt() as usize;
+        res
+    };
 
-    Ok([0, size - 1, grid.len() - size, grid.len() - 1]
+    let path = dfs(vec![], successors, |grid| grid.len() == tiles.len())
+        .ok_or(anyhow!("failed to assemble grid"))?;
+    let grid = path.last().expect("path includes the goal");
+    Ok(grid
         .iter()
-        .map(|&idx| grid[idx].0.number)
-        .product::<usize>())
+        .map(|(tile_idx, orientation)| (tiles[*tile_idx].clone(), *orientation))
+        .collect::<Vec<_>>())
 }
 
-fn rotate(im: Vec<Vec<bool>>) -> Vec<Vec<bool>> {
-    let rows = im.len();
-    let cols = im[0].len();
-
-    let mut new = vec![];
-    for col in 0..cols {
-        let mut new_row = vec![];
-        for row in 0..rows {
-            new_row.push(im[row][col]);
-        }
-        new.push(new_row);
+fn part1(input: &str, options: &Options) -> Result<Answer<usize>> {
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(adjacency(&parse(input)?)));
     }
-    new
-}
+    let grid = find_grid(input)?;
+    let size = (grid.len() as f32).sqrt() as usize;
 
-fn flip(im: Vec<Vec<bool>>) -> Vec<Vec<bool>> {
-    im.into_iter()
-        .map(|row| row.into_iter().rev().collect())
-        .collect()
+    Ok(Answer::Value(
+        [0, size - 1, grid.len() - size, grid.len() - 1]
+            .iter()
+            .map(|&idx| grid[idx].0.number)
+            .product::<usize>(),
+    ))
 }
 
-fn part2(input: &str) -> Result<usize> {
+fn part2(input: &str, options: &Options) -> Result<Answer<usize>> {
+    if dot::wanted(options) {
+        return Ok(Answer::Graph(adjacency(&parse(input)?)));
+    }
     let grid = find_grid(input)?;
     let size = (grid.len() as f32).sqrt() as usize;
-    let tile_size = grid[0].0.size;
     let map = Map { size };
+    // tiles lose their borders when assembled
+    let inner = grid[0].0.size() - 2;
 
-    let mut image: Vec<Vec<&bool>> = vec![];
-    for large_row in 0..size {
-        for tile_row in 1..(tile_size - 1) {
-            let mut row = vec![];
-            for large_column in 0..size {
-                let grid_index = map
-                    .to_index(Coor::new(large_column as _, large_row as _))
-                    .expect("should be in range");
-                let (tile, orientation) = &grid[grid_index];
-                let edge = tile.as_base_edge(*orientation, Edge::Up);
-                let image_row = tile.image_row(edge, tile_row);
-                row.extend_from_slice(&image_row[1..image_row.len() - 1]);
-            }
-            image.push(row);
-        }
-    }
-    let monster = [
-        "                  # ",
-        "#    ##    ##    ###",
-        " #  #  #  #  #  #   ",
-    ]
-    .iter()
-    .map(|l| {
-        l.chars()
-            .map(|c| {
-                Ok(match c {
-                    ' ' => false,
-                    '#' => true,
-                    _ => bail!("invalid tile `{}`", c),
-                })
-            })
-            .collect::<Result<Vec<_>>>()
-    })
-    .collect::<Result<Vec<_>>>()?;
-
-    for &do_flip in &[false, true] {
-        for rotations in 0..4 {
-            let mut sea = image.iter().map(|r| r.clone()).collect::<Vec<_>>();
-            let mut rotation_monsters = 0;
-            let mut rotated_monster = monster.clone();
-            if do_flip {
-                rotated_monster = flip(rotated_monster);
-            }
-            for _ in 0..rotations {
-                rotated_monster = rotate(rotated_monster);
-            }
-
-            for start_row in 0..(&image.len() - rotated_monster.len()) {
-                for start_col in 0..(image[0].len() - rotated_monster[0].len()) {
-                    let mut found_monster = true;
-                    for (row_offset, monster_row) in rotated_monster.iter().enumerate() {
-                        for (col_offset, &monster_val) in monster_row.iter().enumerate() {
-                            let image_row = &image[start_row + row_offset];
-                            if monster_val && !*image_row[start_col + col_offset] {
-                                if !do_flip && rotations == 0 && start_row == 3 && start_col == 2 {}
-                                found_monster = false;
-                            }
-                        }
-                    }
-                    if found_monster {
-                        rotation_monsters += 1;
-                        for (row_offset, monster_row) in rotated_monster.iter().enumerate() {
-                            for (col_offset, monster_val) in monster_row.iter().enumerate() {
-                                if *monster_val {
-                                    sea[start_row + row_offset][start_col + col_offset] = &false;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            if rotation_monsters > 0 {
-                return Ok(sea
-                    .iter()
-                    .map(|r| r.iter().filter(|b| ***b).count())
-                    .sum::<usize>());
-            }
-        }
+    let oriented = grid
+        .iter()
+        .map(|(tile, orientation)| tile.data.transformed(*orientation))
+        .collect::<Vec<_>>();
+    let image = Grid::from_fn(size * inner, size * inner, |coor| {
+        let grid_index = map
+            .to_index(Coor::new(coor.x / inner as i64, coor.y / inner as i64))
+            .expect("should be in range");
+        let within = Coor::new(coor.x % inner as i64 + 1, coor.y % inner as i64 + 1);
+        oriented[grid_index][within]
+    });
+
+    let monster = monster(options)?;
+    let monsters = monster.find_all_orientations(&image);
+    if monsters.is_empty() {
+        bail!("didn't find any sea monsters");
     }
-
-    bail!("didn't find any sea monsters");
+    let mut sea = image;
+    monster.mask(&mut sea, &monsters, Pixel::Off);
+    Ok(Answer::Value(sea.count(|&p| p == Pixel::On)))
 }
 
 #[cfg(test)]
@@ -445,12 +303,15 @@ mod tests {
     fn test_parse() -> Result<()> {
         let input = include_str!("day20.sample");
         let tiles = parse(input)?;
+        assert_eq!(tiles.len(), 9);
+        assert_eq!(tiles[0].number, 2311);
+        assert_eq!(tiles[0].size(), 10);
         Ok(())
     }
 
     #[test]
     fn test_edge_to_num() {
-        assert_eq!(edge_to_num([false, true, false, false].iter(), false), 4)
+        assert_eq!(edge_to_num([false, true, false, false].iter().cloned()), 4)
     }
 
     #[test]
@@ -468,26 +329,59 @@ mod tests {
 ##...##.#.
 ..###.#.#.",
         )?;
-        tile._print();
-        let orientation = Orientation {
-            top: Edge::Left,
-            mirrored: false,
-        };
-        tile.edge_hash(orientation, Edge::Up, true);
+        for orientation in D4::all() {
+            let transformed = tile.data.transformed(orientation);
+            for &edge in &EDGES {
+                let expected = transformed.edge(edge).into_iter().map(|p| p == Pixel::On);
+                assert_eq!(tile.edge_hash(orientation, edge), edge_to_num(expected));
+            }
+        }
+        // rotating clockwise moves the left edge, read bottom to top, to the top
+        let rotated = D4::new(false, 1);
+        assert_eq!(tile.edge_hash(rotated, Edge::Top), 0b0110001111);
         Ok(())
     }
 
     #[test]
     fn test_part1() -> Result<()> {
         let input = include_str!("day20.sample");
-        assert_eq!(part1(input)?, 20899048083289);
+        assert_eq!(part1(input, &Options::new())?, 20899048083289);
         Ok(())
     }
 
     #[test]
     fn test_part2() -> Result<()> {
         let input = include_str!("day20.sample");
-        assert_eq!(part2(input)?, 273);
+        assert_eq!(part2(input, &Options::new())?, 273);
+        Ok(())
+    }
+
+    #[test]
+    fn test_monster() -> Result<()> {
+        let monster = monster(&Options::new())?;
+        assert_eq!(monster.width(), 20);
+        assert_eq!(monster.height(), 3);
+
+        let path = std::env::temp_dir().join(format!("aoc2020-day20-{}", std::process::id()));
+        fs::write(&path, MONSTER)?;
+        let input = include_str!("day20.sample");
+        let options = Options::new().with("monster", Some(&path.display().to_string()));
+        assert_eq!(part2(input, &options)?, 273);
+        fs::remove_file(&path)?;
+        assert!(part2(input, &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot() -> Result<()> {
+        let input = include_str!("day20.sample");
+        let dot = part1(input, &Options::new().with("dot", None))?.to_string();
+        // a 3x3 grid has 12 joins
+        assert_eq!(dot.matches(" -- ").count(), 12);
+        assert!(dot.contains("        label=\"corners\";\n        \"1951\" [label=\"Tile 1951\"];"));
+        assert!(dot.contains(
+            "        label=\"interior\";\n        \"1427\" [label=\"Tile 1427\"];\n    }"
+        ));
         Ok(())
     }
 }
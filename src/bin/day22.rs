@@ -1,6 +1,7 @@
 use anyhow::{anyhow, bail, Result};
+use aoc2020::cycle::{History, Outcome};
 use aoc2020::dispatch;
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 
 fn main() -> Result<()> {
     dispatch(part1, part2)
@@ -68,47 +69,44 @@ enum Player {
     P2,
 }
 
-fn game(mut p1_cards: Deck, mut p2_cards: Deck, number: usize) -> (Player, usize) {
-    let mut seen = HashSet::new();
-    let mut _round_number = 1;
-    while p1_cards.len() > 0 && p2_cards.len() > 0 {
-        // println!("\nRound {} (Game {})", _round_number, number);
-        // println!("P1: {:?}", p1_cards);
-        // println!("P2: {:?}", p2_cards);
-        if seen.contains(&(p1_cards.clone(), p2_cards.clone())) {
-            return (Player::P1, score(&p1_cards));
-        }
-        seen.insert((p1_cards.clone(), p2_cards.clone()));
-        let p1_card = p1_cards.pop_front().expect("not empty");
-        let p2_card = p2_cards.pop_front().expect("not empty");
-        // println!("P1 plays {}", p1_card);
-        // println!("P2 plays {}", p2_card);
-
-        let winner = round(
-            p1_card, &p1_cards, p2_card, &p2_cards, number, /*, &mut seen*/
-        );
-        match winner {
-            Player::P1 => {
-                // println!("P1 wins round {} of game {}", _round_number, number);
-                p1_cards.push_back(p1_card);
-                p1_cards.push_back(p2_card);
+fn game(p1_cards: Deck, p2_cards: Deck, number: usize) -> (Player, usize) {
+    let history = History::run(
+        (p1_cards, p2_cards),
+        |(p1_cards, p2_cards)| {
+            if p1_cards.is_empty() || p2_cards.is_empty() {
+                return None;
+            }
+            let mut p1_cards = p1_cards.clone();
+            let mut p2_cards = p2_cards.clone();
+            let p1_card = p1_cards.pop_front().expect("not empty");
+            let p2_card = p2_cards.pop_front().expect("not empty");
+
+            match round(p1_card, &p1_cards, p2_card, &p2_cards, number) {
+                Player::P1 => {
+                    p1_cards.push_back(p1_card);
+                    p1_cards.push_back(p2_card);
+                }
+                Player::P2 => {
+                    p2_cards.push_back(p2_card);
+                    p2_cards.push_back(p1_card);
+                }
             }
-            Player::P2 => {
-                // println!("P2 wins round {} of game {}", _round_number, number);
-                p2_cards.push_back(p2_card);
-                p2_cards.push_back(p1_card);
+            Some((p1_cards, p2_cards))
+        },
+        None,
+    );
+    match history.outcome() {
+        // a repeated position is a win for player 1
+        Outcome::Cycle(cycle) => (Player::P1, score(&history.states()[cycle.offset].0)),
+        Outcome::Terminated(_) => {
+            let (p1_cards, p2_cards) = history.last();
+            if p1_cards.is_empty() {
+                (Player::P2, score(p2_cards))
+            } else {
+                (Player::P1, score(p1_cards))
             }
         }
-        _round_number += 1;
-    }
-    if p1_cards.len() == 0 {
-        // println!("The winner of game {} is P2", number);
-        (Player::P2, score(&p2_cards))
-    } else if p2_cards.len() == 0 {
-        // println!("The winner of game {} is P1", number);
-        (Player::P1, score(&p1_cards))
-    } else {
-        panic!("someone should have won by now")
+        Outcome::Unresolved => unreachable!("games run without a limit"),
     }
 }
 
@@ -170,4 +168,12 @@ Player 2:
         assert_eq!(part2(INPUT)?, 291);
         Ok(())
     }
+
+    #[test]
+    fn test_infinite_game() -> Result<()> {
+        let (p1_cards, p2_cards) = parse("Player 1:\n43\n19\n\nPlayer 2:\n2\n29\n14")?;
+        let (winner, _) = game(p1_cards, p2_cards, 1);
+        assert!(matches!(winner, Player::P1));
+        Ok(())
+    }
 }
@@ -1,11 +1,25 @@
 use anyhow::{bail, Error, Result};
-use aoc2020::dispatch;
-use std::collections::{HashMap, HashSet};
+use aoc2020::automaton::{Backend, InfiniteAutomaton, LifeRule};
+use aoc2020::coor::Hex;
+use aoc2020::shared::{dispatch_with, Opt, Options};
+use aoc2020::sparse_grid::SparseGrid;
+use aoc2020::visualise::{Palette, Visualiser, VISUALISE};
+use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::ops::Add;
 
 fn main() -> Result<()> {
-    dispatch(part1, part2)
+    dispatch_with(
+        part1,
+        part2,
+        &[
+            Opt {
+                name: "backend",
+                help: "How to store the floor: `sparse` (default) or `dense`",
+                value: Some("BACKEND"),
+            },
+            VISUALISE,
+        ],
+    )
 }
 
 #[derive(Debug, PartialEq)]
@@ -19,15 +33,15 @@ enum Direction {
 }
 
 impl Direction {
-    fn as_coor(&self) -> Coor {
+    fn as_coor(&self) -> Hex {
         use Direction::*;
         match self {
-            E => Coor { x: 1, y: -1, z: 0 },
-            W => Coor { x: -1, y: 1, z: 0 },
-            NW => Coor { x: 0, y: 1, z: -1 },
-            SE => Coor { x: 0, y: -1, z: 1 },
-            NE => Coor { x: 1, y: 0, z: -1 },
-            SW => Coor { x: -1, y: 0, z: 1 },
+            E => Hex::new(1, -1, 0),
+            W => Hex::new(-1, 1, 0),
+            NW => Hex::new(0, 1, -1),
+            SE => Hex::new(0, -1, 1),
+            NE => Hex::new(1, 0, -1),
+            SW => Hex::new(-1, 0, 1),
         }
     }
 }
@@ -70,75 +84,16 @@ fn parse(input: &str) -> Result<Vec<Direction>> {
     Ok(res)
 }
 
-#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Hash)]
-struct Coor {
-    x: i64,
-    y: i64,
-    z: i64,
-}
-
-struct NeighbourIterator<'a> {
-    coor: &'a Coor,
-    pos: usize,
-}
-
-impl<'a> NeighbourIterator<'a> {
-    fn new(coor: &'a Coor) -> Self {
-        Self { coor, pos: 0 }
-    }
-}
-
-impl<'a> Iterator for NeighbourIterator<'a> {
-    type Item = Coor;
-    fn next(&mut self) -> Option<Self::Item> {
-        let positions = [
-            (1, -1, 0),
-            (-1, 1, 0),
-            (1, 0, -1),
-            (-1, 0, 1),
-            (0, 1, -1),
-            (0, -1, 1),
-        ];
-        let pos = self.pos;
-        self.pos += 1;
-        positions.get(pos).map(|(x, y, z)| {
-            Coor {
-                x: *x,
-                y: *y,
-                z: *z,
-            } + *self.coor
-        })
-    }
-}
-
-impl Coor {
-    fn neighbours(&self) -> NeighbourIterator {
-        NeighbourIterator::new(self)
-    }
-}
-
-impl Add for Coor {
-    type Output = Coor;
-
-    fn add(self, other: Coor) -> Coor {
-        Coor {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-        }
-    }
-}
-
 type Tile = Vec<Direction>;
 
-fn get_floor(input: &str) -> Result<HashSet<Coor>> {
+fn get_floor(input: &str) -> Result<SparseGrid<Hex>> {
     let mut colors = HashMap::new();
     let tiles = input
         .split('\n')
         .map(|s| parse(s))
         .collect::<Result<Vec<Tile>>>()?;
     for tile in tiles {
-        let mut coor = Coor { x: 0, y: 0, z: 0 };
+        let mut coor = Hex::default();
         for direction in tile {
             coor = coor + direction.as_coor();
         }
@@ -149,54 +104,39 @@ fn get_floor(input: &str) -> Result<HashSet<Coor>> {
         .iter()
         .filter(|(_, v)| **v)
         .map(|(c, _)| *c)
-        .collect::<HashSet<Coor>>())
+        .collect())
 }
 
-fn part1(input: &str) -> Result<usize> {
+fn part1(input: &str, _options: &Options) -> Result<usize> {
     let colors = get_floor(input)?;
     Ok(colors.len())
 }
 
-fn flip(black_tiles: HashSet<Coor>) -> HashSet<Coor> {
-    let mut next = HashSet::new();
+const DAYS: usize = 100;
 
-    let black_neighbours = |tile: &Coor| {
-        tile.neighbours()
-            .map(|n| black_tiles.contains(&n))
-            .filter(|t| *t)
-            .count()
-    };
-
-    for tile in &black_tiles {
-        for neighbour in tile.neighbours() {
-            if black_tiles.contains(&neighbour) {
-                continue;
-            }
+// black tiles with zero or more than 2 black neighbours turn white, white tiles with exactly 2
+// black neighbours turn black
+fn floor_automaton(input: &str, backend: Backend) -> Result<InfiniteAutomaton<Hex, LifeRule>> {
+    InfiniteAutomaton::new(backend, get_floor(input)?, "B2/S12".parse()?)
+}
 
-            let bc = black_neighbours(&neighbour);
-            if bc == 2 {
-                next.insert(neighbour.clone());
+fn part2(input: &str, options: &Options) -> Result<usize> {
+    let backend = options.parse("backend")?.unwrap_or_default();
+    let mut floor = floor_automaton(input, backend)?;
+    let palette = Palette::new(&[('#', [20, 20, 20]), ('.', [240, 240, 240])]);
+    match Visualiser::from_options(options, palette)? {
+        Some(mut visualiser) => {
+            let render =
+                |tiles: SparseGrid<Hex>| tiles.render(|t| if t.is_some() { '#' } else { '.' });
+            visualiser.frame(&render(floor.live()))?;
+            for _ in 0..DAYS {
+                floor.step();
+                visualiser.frame(&render(floor.live()))?;
             }
         }
+        None => floor.run(DAYS),
     }
-
-    for tile in &black_tiles {
-        let bc = black_neighbours(tile);
-        if bc == 0 || bc > 2 {
-        } else {
-            next.insert(tile.clone());
-        }
-    }
-
-    next
-}
-
-fn part2(input: &str) -> Result<usize> {
-    let mut colors = get_floor(input)?;
-    for _ in 0..100 {
-        colors = flip(colors);
-    }
-    Ok(colors.len())
+    Ok(floor.population())
 }
 
 #[cfg(test)]
@@ -226,19 +166,34 @@ wseweeenwnesenwwwswnew";
 
     #[test]
     fn test_part1() -> Result<()> {
-        assert_eq!(part1(INPUT)?, 10);
+        assert_eq!(part1(INPUT, &Options::new())?, 10);
         Ok(())
     }
 
     #[test]
     fn test_part2() -> Result<()> {
-        assert_eq!(part2(INPUT)?, 2208);
+        assert_eq!(part2(INPUT, &Options::new())?, 2208);
+        let options = Options::new().with("backend", Some("dense"));
+        assert_eq!(part2(INPUT, &options)?, 2208);
+        Ok(())
+    }
+
+    #[test]
+    fn test_days() -> Result<()> {
+        let mut floor = floor_automaton(INPUT, Backend::Sparse)?;
+        let mut dense = floor_automaton(INPUT, Backend::Dense)?;
+        for &expected in &[15, 12, 25, 14, 23] {
+            floor.step();
+            dense.step();
+            assert_eq!(floor.population(), expected);
+            assert_eq!(dense.live(), floor.live());
+        }
         Ok(())
     }
 
     #[test]
     fn test_part1a() -> Result<()> {
-        assert_eq!(part1("nwwswee")?, 1);
+        assert_eq!(part1("nwwswee", &Options::new())?, 1);
         Ok(())
     }
 
@@ -1,38 +1,33 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use aoc2020::dispatch;
-use aoc2020::mod_arith::mod_mul;
+use aoc2020::mod_arith::discrete_log;
+use aoc2020::mod_int::ModInt;
 
-const REM: usize = 20201227;
+const REM: i64 = 20201227;
 
 fn main() -> Result<()> {
     dispatch(part1, part2)
 }
 
-fn transform(subj: usize, loop_size: usize) -> usize {
-    let mut val = 1;
-    for _ in 0..loop_size {
-        val = mod_mul(val, subj as i64, REM as i64);
-    }
-    val as usize
+fn transform(subj: i64, loop_size: u64) -> i64 {
+    ModInt::<REM>::new(subj).pow(loop_size).value()
 }
 
-fn find_loop(key: usize) -> usize {
-    let mut val = 1;
-    let mut loop_size = 1;
-    loop {
-        val = mod_mul(val, 7, REM as i64);
-        if val == key as i64 {
-            break loop_size;
-        }
-        loop_size += 1;
+fn find_loop(key: i64) -> Result<u64> {
+    // transforming always gives a remainder
+    if !(0..REM).contains(&key) {
+        bail!("public key {} is out of range", key);
     }
+    discrete_log(7, key, REM)
+        .map(|loop_size| loop_size as u64)
+        .ok_or_else(|| anyhow!("no loop size gives the public key {}", key))
 }
 
-fn part1(input: &str) -> Result<usize> {
+fn part1(input: &str) -> Result<i64> {
     let mut lines = input.split('\n');
-    let n1: usize = lines.next().expect("should have 2 numbers").parse()?;
-    let n2: usize = lines.next().expect("should have 2 numbers").parse()?;
-    let loop_size = find_loop(n1);
+    let n1: i64 = lines.next().expect("should have 2 numbers").parse()?;
+    let n2: i64 = lines.next().expect("should have 2 numbers").parse()?;
+    let loop_size = find_loop(n1)?;
     Ok(transform(n2, loop_size))
 }
 
@@ -49,4 +44,15 @@ mod tests {
         assert_eq!(part1("5764801\n17807724")?, 14897079);
         Ok(())
     }
+
+    #[test]
+    fn test_transform() -> Result<()> {
+        assert_eq!(find_loop(5764801)?, 8);
+        assert_eq!(find_loop(17807724)?, 11);
+        assert_eq!(transform(7, 8), 5764801);
+        assert_eq!(transform(5764801, 11), 14897079);
+        assert!(find_loop(0).is_err());
+        assert!(find_loop(REM + 5).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,390 @@
+use crate::coor::{Coor, CoorN, Hex};
+use crate::sparse_grid::{Bounded, SparseGrid};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+pub const WORD_BITS: usize = 64;
+
+// coordinates that embed in the integer lattice Z^DIMS, so they can be stored densely
+pub trait Lattice: Bounded {
+    const DIMS: usize;
+
+    fn component(&self, axis: usize) -> i64;
+
+    fn from_components(components: &[i64]) -> Self;
+}
+
+impl Lattice for Coor {
+    const DIMS: usize = 2;
+
+    fn component(&self, axis: usize) -> i64 {
+        match axis {
+            0 => self.x,
+            1 => self.y,
+            _ => panic!("no axis {} in 2 dimensions", axis),
+        }
+    }
+
+    fn from_components(components: &[i64]) -> Self {
+        Coor::new(components[0], components[1])
+    }
+}
+
+impl<const N: usize> Lattice for CoorN<N> {
+    const DIMS: usize = N;
+
+    fn component(&self, axis: usize) -> i64 {
+        self.0[axis]
+    }
+
+    fn from_components(components: &[i64]) -> Self {
+        let mut res = [0; N];
+        res.copy_from_slice(components);
+        CoorN(res)
+    }
+}
+
+// axial coordinates; y follows from x + y + z == 0
+impl Lattice for Hex {
+    const DIMS: usize = 2;
+
+    fn component(&self, axis: usize) -> i64 {
+        match axis {
+            0 => self.x,
+            1 => self.z,
+            _ => panic!("no axis {} in 2 dimensions", axis),
+        }
+    }
+
+    fn from_components(components: &[i64]) -> Self {
+        Hex::new(components[0], -components[0] - components[1], components[1])
+    }
+}
+
+// a set of lattice points inside a box, packed one bit per cell. the first axis runs along
+// the bits of a row of words; every combination of the other components gets its own row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid<C> {
+    origin: Vec<i64>,
+    extents: Vec<usize>,
+    words_per_row: usize,
+    rows: usize,
+    words: Vec<u64>,
+    coor: PhantomData<C>,
+}
+
+impl<C: Lattice> BitGrid<C> {
+    // an empty box of `extents` cells from `origin`. the first extent is rounded up to whole
+    // words
+    pub fn new(origin: Vec<i64>, mut extents: Vec<usize>) -> Self {
+        assert_eq!(origin.len(), C::DIMS, "origin has the wrong dimensions");
+        assert_eq!(extents.len(), C::DIMS, "extents have the wrong dimensions");
+        let words_per_row = extents[0].div_ceil(WORD_BITS);
+        extents[0] = words_per_row * WORD_BITS;
+        let rows = extents[1..].iter().product();
+        BitGrid {
+            origin,
+            extents,
+            words_per_row,
+            rows,
+            words: vec![0; rows * words_per_row],
+            coor: PhantomData,
+        }
+    }
+
+    pub fn origin(&self) -> &[i64] {
+        &self.origin
+    }
+
+    pub fn extents(&self) -> &[usize] {
+        &self.extents
+    }
+
+    pub fn words_per_row(&self) -> usize {
+        self.words_per_row
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut [u64] {
+        &mut self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    // how many rows apart two cells differing by one along `axis` are. the first axis doesn't
+    // have a stride as it runs within rows
+    pub fn row_stride(&self, axis: usize) -> usize {
+        assert!(axis > 0, "the first axis runs within rows");
+        self.extents[1..axis].iter().product()
+    }
+
+    // the components of `row` relative to the origin, with 0 for the first axis
+    pub fn row_components(&self, mut row: usize) -> Vec<usize> {
+        let mut res = vec![0; C::DIMS];
+        for (component, extent) in res.iter_mut().zip(self.extents.iter()).skip(1) {
+            *component = row % extent;
+            row /= extent;
+        }
+        res
+    }
+
+    fn position(&self, coor: &C) -> Option<(usize, usize)> {
+        let mut relative = vec![0; C::DIMS];
+        for (axis, r) in relative.iter_mut().enumerate() {
+            let offset = coor.component(axis) - self.origin[axis];
+            if offset < 0 || offset as usize >= self.extents[axis] {
+                return None;
+            }
+            *r = offset as usize;
+        }
+        let row = (1..C::DIMS)
+            .rev()
+            .fold(0, |row, axis| row * self.extents[axis] + relative[axis]);
+        Some((row, relative[0]))
+    }
+
+    pub fn contains(&self, coor: &C) -> bool {
+        match self.position(coor) {
+            Some((row, bit)) => self.row(row)[bit / WORD_BITS] >> (bit % WORD_BITS) & 1 == 1,
+            None => false,
+        }
+    }
+
+    // grows the box if `coor` lies outside. returns whether the point was new
+    pub fn insert(&mut self, coor: C) -> bool {
+        let (row, bit) = match self.position(&coor) {
+            Some(position) => position,
+            None => {
+                *self = self.reframed(self.bounds_with(&coor), 0);
+                self.position(&coor).expect("box was grown to fit")
+            }
+        };
+        let word = &mut self.row_mut(row)[bit / WORD_BITS];
+        let mask = 1 << (bit % WORD_BITS);
+        let new = *word & mask == 0;
+        *word |= mask;
+        new
+    }
+
+    pub fn remove(&mut self, coor: &C) -> bool {
+        match self.position(coor) {
+            Some((row, bit)) => {
+                let word = &mut self.row_mut(row)[bit / WORD_BITS];
+                let mask = 1 << (bit % WORD_BITS);
+                let present = *word & mask != 0;
+                *word &= !mask;
+                present
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = C> + '_ {
+        (0..self.rows).flat_map(move |row| {
+            let mut components = self.row_components(row);
+            self.row(row)
+                .iter()
+                .enumerate()
+                .flat_map(|(w, &word)| {
+                    (0..WORD_BITS)
+                        .filter(move |bit| word >> bit & 1 == 1)
+                        .map(move |bit| w * WORD_BITS + bit)
+                })
+                .map(move |bit| {
+                    components[0] = bit;
+                    let absolute = components
+                        .iter()
+                        .zip(self.origin.iter())
+                        .map(|(&c, o)| c as i64 + o)
+                        .collect::<Vec<_>>();
+                    C::from_components(&absolute)
+                })
+        })
+    }
+
+    // the smallest box holding every point, as inclusive components relative to the origin
+    pub fn occupied(&self) -> Option<(Vec<usize>, Vec<usize>)> {
+        let mut res: Option<(Vec<usize>, Vec<usize>)> = None;
+        for row in 0..self.rows {
+            let words = self.row(row);
+            let first = match words.iter().position(|&w| w != 0) {
+                Some(first) => first,
+                None => continue,
+            };
+            let last = words
+                .iter()
+                .rposition(|&w| w != 0)
+                .expect("row isn't empty");
+            let mut low = self.row_components(row);
+            let mut high = low.clone();
+            low[0] = first * WORD_BITS + words[first].trailing_zeros() as usize;
+            high[0] = last * WORD_BITS + WORD_BITS - 1 - words[last].leading_zeros() as usize;
+            res = Some(match res {
+                Some((min, max)) => (
+                    min.iter().zip(low).map(|(&a, b)| a.min(b)).collect(),
+                    max.iter().zip(high).map(|(&a, b)| a.max(b)).collect(),
+                ),
+                None => (low, high),
+            });
+        }
+        res
+    }
+
+    // whether every point is at least `margin` cells from the edge of the box
+    pub fn has_margin(&self, margin: usize) -> bool {
+        match self.occupied() {
+            Some((min, max)) => (0..C::DIMS)
+                .all(|axis| min[axis] >= margin && max[axis] + margin < self.extents[axis]),
+            None => true,
+        }
+    }
+
+    // a copy with the box shrunk or grown to leave `margin` cells around the points
+    pub fn with_margin(&self, margin: usize) -> Self {
+        let bounds = match self.occupied() {
+            Some((min, max)) => (0..C::DIMS)
+                .map(|axis| {
+                    (
+                        self.origin[axis] + min[axis] as i64,
+                        self.origin[axis] + max[axis] as i64,
+                    )
+                })
+                .collect(),
+            None => vec![(0, 0); C::DIMS],
+        };
+        self.reframed(bounds, margin)
+    }
+
+    // the absolute bounds of the box, extended to include `coor`
+    fn bounds_with(&self, coor: &C) -> Vec<(i64, i64)> {
+        (0..C::DIMS)
+            .map(|axis| {
+                let low = self.origin[axis];
+                let high = low + self.extents[axis] as i64 - 1;
+                let c = coor.component(axis);
+                (low.min(c), high.max(c))
+            })
+            .collect()
+    }
+
+    fn reframed(&self, bounds: Vec<(i64, i64)>, margin: usize) -> Self {
+        let margin = margin as i64;
+        let origin = bounds.iter().map(|(low, _)| low - margin).collect();
+        let extents = bounds
+            .iter()
+            .map(|(low, high)| (high - low + 1 + 2 * margin) as usize)
+            .collect();
+        let mut res = BitGrid::new(origin, extents);
+        for coor in self.iter() {
+            res.insert(coor);
+        }
+        res
+    }
+}
+
+impl<C: Lattice> FromIterator<C> for BitGrid<C> {
+    fn from_iter<I: IntoIterator<Item = C>>(iter: I) -> Self {
+        let coors = iter.into_iter().collect::<Vec<_>>();
+        let bounds = match coors.first() {
+            Some(first) => (0..C::DIMS)
+                .map(|axis| {
+                    coors.iter().map(|c| c.component(axis)).fold(
+                        (first.component(axis), first.component(axis)),
+                        |(low, high), c| (low.min(c), high.max(c)),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            None => vec![(0, 0); C::DIMS],
+        };
+        let mut res = BitGrid::new(
+            bounds.iter().map(|(low, _)| *low).collect(),
+            bounds
+                .iter()
+                .map(|(low, high)| (high - low + 1) as usize)
+                .collect(),
+        );
+        for coor in coors {
+            res.insert(coor);
+        }
+        res
+    }
+}
+
+impl<C: Lattice> From<&BitGrid<C>> for SparseGrid<C> {
+    fn from(grid: &BitGrid<C>) -> Self {
+        grid.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_grow() {
+        let mut grid: BitGrid<Coor> = [Coor::new(0, 0), Coor::new(3, 1)].iter().cloned().collect();
+        assert_eq!(grid.extents(), &[64, 2]);
+        assert!(grid.contains(&Coor::new(3, 1)));
+        assert!(!grid.contains(&Coor::new(1, 0)));
+        assert!(!grid.contains(&Coor::new(-1, 0)));
+
+        assert!(grid.insert(Coor::new(-70, 5)));
+        assert!(!grid.insert(Coor::new(-70, 5)));
+        assert_eq!(grid.origin(), &[-70, 0]);
+        assert_eq!(grid.extents(), &[192, 6]);
+        assert_eq!(grid.len(), 3);
+        assert_eq!(grid.occupied(), Some((vec![0, 0], vec![73, 5])));
+
+        assert!(grid.remove(&Coor::new(0, 0)));
+        assert!(!grid.remove(&Coor::new(100, 100)));
+        let sparse: SparseGrid<Coor> = (&grid).into();
+        assert_eq!(
+            sparse,
+            [Coor::new(3, 1), Coor::new(-70, 5)]
+                .iter()
+                .cloned()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_margin() {
+        let grid: BitGrid<CoorN<3>> = [CoorN::new([1, 2, 3]), CoorN::new([2, 2, -1])]
+            .iter()
+            .cloned()
+            .collect();
+        assert!(!grid.has_margin(1));
+        let framed = grid.with_margin(2);
+        assert!(framed.has_margin(2));
+        assert_eq!(framed.origin(), &[-1, 0, -3]);
+        assert_eq!(framed.extents(), &[64, 5, 9]);
+        assert_eq!(framed.row_stride(2), 5);
+        assert_eq!(
+            framed.iter().collect::<SparseGrid<_>>(),
+            grid.iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        let hexes = [Hex::new(1, -1, 0), Hex::new(-2, 0, 2), Hex::default()];
+        let grid: BitGrid<Hex> = hexes.iter().cloned().collect();
+        assert_eq!(
+            grid.iter().collect::<SparseGrid<_>>(),
+            hexes.iter().cloned().collect()
+        );
+    }
+}
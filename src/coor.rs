@@ -41,3 +41,101 @@ impl Mul<i64> for Coor {
         Coor::new(self.x * rhs, self.y * rhs)
     }
 }
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+pub struct CoorN<const N: usize>(pub [i64; N]);
+
+impl<const N: usize> CoorN<N> {
+    pub const fn new(components: [i64; N]) -> Self {
+        CoorN(components)
+    }
+
+    // all 3^N - 1 points differing by at most one in every component
+    pub fn neighbours(&self) -> impl Iterator<Item = CoorN<N>> {
+        let coor = *self;
+        (0..3usize.pow(N as u32))
+            .map(|mut idx| {
+                let mut delta = [0; N];
+                for d in delta.iter_mut() {
+                    *d = (idx % 3) as i64 - 1;
+                    idx /= 3;
+                }
+                CoorN(delta)
+            })
+            .filter(|delta| *delta != CoorN::default())
+            .map(move |delta| coor + delta)
+    }
+}
+
+impl<const N: usize> Default for CoorN<N> {
+    fn default() -> Self {
+        CoorN([0; N])
+    }
+}
+
+impl<const N: usize> fmt::Debug for CoorN<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for (i, c) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<const N: usize> Add for CoorN<N> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut res = self.0;
+        for (r, o) in res.iter_mut().zip(other.0.iter()) {
+            *r += o;
+        }
+        CoorN(res)
+    }
+}
+
+// cube coordinates for a hexagonal grid; invariant: x + y + z == 0
+#[derive(PartialEq, Eq, Default, Clone, Copy, Hash)]
+pub struct Hex {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+const HEX_DIRECTIONS: [Hex; 6] = [
+    Hex::new(1, -1, 0),
+    Hex::new(-1, 1, 0),
+    Hex::new(1, 0, -1),
+    Hex::new(-1, 0, 1),
+    Hex::new(0, 1, -1),
+    Hex::new(0, -1, 1),
+];
+
+impl Hex {
+    pub const fn new(x: i64, y: i64, z: i64) -> Self {
+        Hex { x, y, z }
+    }
+
+    pub fn neighbours(&self) -> impl Iterator<Item = Hex> {
+        let hex = *self;
+        HEX_DIRECTIONS.iter().map(move |&d| hex + d)
+    }
+}
+
+impl fmt::Debug for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl Add for Hex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Hex::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// the state at step `offset` recurs at step `offset + period`, and from there on every
+// `period` steps
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cycle {
+    pub offset: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    pub fn is_fixed_point(&self) -> bool {
+        self.period == 1
+    }
+
+    // the earliest step with the same state as step `n`
+    pub fn equivalent_step(&self, n: usize) -> usize {
+        if n < self.offset {
+            n
+        } else {
+            self.offset + (n - self.offset) % self.period
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    // the step function gave up after this many steps
+    Terminated(usize),
+    Cycle(Cycle),
+    // the step limit was reached without a repeat
+    Unresolved,
+}
+
+// every distinct state of a simulation, in order
+#[derive(Debug, Clone)]
+pub struct History<S> {
+    states: Vec<S>,
+    outcome: Outcome,
+}
+
+fn hash<S: Hash>(state: &S) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<S: Eq + Hash> History<S> {
+    // runs `step` until it returns `None`, a state repeats, or `limit` steps have been taken
+    pub fn run<F>(initial: S, mut step: F, limit: Option<usize>) -> Self
+    where
+        F: FnMut(&S) -> Option<S>,
+    {
+        // states are looked up by hash, and only compared in full on a collision
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        seen.insert(hash(&initial), vec![0]);
+        let mut states = vec![initial];
+        let outcome = loop {
+            let steps = states.len() - 1;
+            if limit.is_some_and(|limit| steps >= limit) {
+                break Outcome::Unresolved;
+            }
+            let next = match step(states.last().expect("never empty")) {
+                Some(next) => next,
+                None => break Outcome::Terminated(steps),
+            };
+            let key = hash(&next);
+            let indices = seen.entry(key).or_default();
+            if let Some(&offset) = indices.iter().find(|&&i| states[i] == next) {
+                break Outcome::Cycle(Cycle {
+                    offset,
+                    period: states.len() - offset,
+                });
+            }
+            indices.push(states.len());
+            states.push(next);
+        };
+        History { states, outcome }
+    }
+
+    // for simulations that always have a next state
+    pub fn iterate<F>(initial: S, mut step: F, limit: Option<usize>) -> Self
+    where
+        F: FnMut(&S) -> S,
+    {
+        History::run(initial, |s| Some(step(s)), limit)
+    }
+}
+
+impl<S> History<S> {
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        match self.outcome {
+            Outcome::Cycle(cycle) => Some(cycle),
+            _ => None,
+        }
+    }
+
+    pub fn states(&self) -> &[S] {
+        &self.states
+    }
+
+    // the last distinct state, i.e. the final one unless the simulation cycled
+    pub fn last(&self) -> &S {
+        self.states.last().expect("never empty")
+    }
+
+    // extrapolated from the cycle if `step` wasn't simulated
+    pub fn state_at(&self, step: usize) -> Option<&S> {
+        if let Some(state) = self.states.get(step) {
+            return Some(state);
+        }
+        self.cycle()
+            .map(|cycle| &self.states[cycle.equivalent_step(step)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle() {
+        // 0, 1, 2, 3, 4, 5, 3, 4, 5, ...
+        let history = History::iterate(0, |&n| if n == 5 { 3 } else { n + 1 }, None);
+        let cycle = Cycle {
+            offset: 3,
+            period: 3,
+        };
+        assert_eq!(history.outcome(), Outcome::Cycle(cycle));
+        assert!(!cycle.is_fixed_point());
+        assert_eq!(history.states().len(), 6);
+        assert_eq!(history.state_at(6), Some(&3));
+        assert_eq!(history.state_at(1_000_000_001), Some(&5));
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let history = History::iterate(100, |&n| (n / 2).max(3), None);
+        let cycle = history.cycle().expect("should settle");
+        assert!(cycle.is_fixed_point());
+        assert_eq!(*history.last(), 3);
+        assert_eq!(history.state_at(1000), Some(&3));
+    }
+
+    #[test]
+    fn test_termination_and_limit() {
+        let history = History::run(0, |&n| if n < 10 { Some(n + 1) } else { None }, None);
+        assert_eq!(history.outcome(), Outcome::Terminated(10));
+        assert_eq!(history.state_at(11), None);
+
+        let history = History::iterate(0u64, |&n| n + 1, Some(100));
+        assert_eq!(history.outcome(), Outcome::Unresolved);
+        assert_eq!(*history.last(), 100);
+    }
+}
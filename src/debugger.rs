@@ -0,0 +1,504 @@
+use crate::vm::{InstructionSet, Program, RunResult, Vm};
+use anyhow::{anyhow, bail, Error, Result};
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+const HELP: &str = "commands:
+  step [N]            run N instructions (default 1)
+  continue            run until a breakpoint or the program stops
+  break PC            stop before running the instruction at PC
+  break REG OP VALUE  stop when a register compares, e.g. `break acc > 10`
+  delete N            remove breakpoint N
+  breakpoints         list breakpoints
+  watch REG           report whenever a register changes
+  unwatch REG         stop watching a register
+  trace [N]           show the last N instructions run (default all)
+  loop                show where the program started looping
+  print               show the pc, registers and step count
+  help                show this
+  quit                stop debugging";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds(&self, a: i64, b: i64) -> bool {
+        match self {
+            Comparison::Eq => a == b,
+            Comparison::Ne => a != b,
+            Comparison::Lt => a < b,
+            Comparison::Le => a <= b,
+            Comparison::Gt => a > b,
+            Comparison::Ge => a >= b,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "==" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            _ => bail!("unknown comparison `{}`", s),
+        })
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// checked before each instruction runs
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Breakpoint {
+    Pc(usize),
+    Register {
+        name: String,
+        comparison: Comparison,
+        value: i64,
+    },
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Pc(pc) => write!(f, "pc {}", pc),
+            Breakpoint::Register {
+                name,
+                comparison,
+                value,
+            } => write!(f, "{} {} {}", name, comparison, value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Break(Breakpoint),
+    Delete(usize),
+    Breakpoints,
+    Watch(String),
+    Unwatch(String),
+    Trace(Option<usize>),
+    Loop,
+    Print,
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let number = |word: &str| -> Result<usize> {
+            word.parse()
+                .map_err(|_| anyhow!("expected a number, not `{}`", word))
+        };
+        Ok(match words.as_slice() {
+            ["step"] | ["s"] => Command::Step(1),
+            ["step", n] | ["s", n] => Command::Step(number(n)?),
+            ["continue"] | ["c"] => Command::Continue,
+            ["break", pc] | ["b", pc] => Command::Break(Breakpoint::Pc(number(pc)?)),
+            ["break", name, comparison, value] | ["b", name, comparison, value] => {
+                Command::Break(Breakpoint::Register {
+                    name: name.to_string(),
+                    comparison: comparison.parse()?,
+                    value: value
+                        .parse()
+                        .map_err(|_| anyhow!("expected a value, not `{}`", value))?,
+                })
+            }
+            ["delete", n] | ["d", n] => Command::Delete(number(n)?),
+            ["breakpoints"] => Command::Breakpoints,
+            ["watch", name] | ["w", name] => Command::Watch(name.to_string()),
+            ["unwatch", name] => Command::Unwatch(name.to_string()),
+            ["trace"] | ["t"] => Command::Trace(None),
+            ["trace", n] | ["t", n] => Command::Trace(Some(number(n)?)),
+            ["loop"] | ["l"] => Command::Loop,
+            ["print"] | ["p"] => Command::Print,
+            ["help"] | ["h"] => Command::Help,
+            ["quit"] | ["q"] => Command::Quit,
+            _ => bail!("unknown command `{}`, try `help`", s.trim()),
+        })
+    }
+}
+
+// an instruction that ran, and the registers it left behind
+#[derive(Debug, Clone)]
+struct TraceEntry<I> {
+    step: usize,
+    pc: usize,
+    instruction: I,
+    registers: Vec<i64>,
+}
+
+pub struct Debugger<'a, I> {
+    vm: Vm<'a, I>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<String>,
+    trace: Vec<TraceEntry<I>>,
+}
+
+impl<'a, I: InstructionSet> Debugger<'a, I> {
+    pub fn new(program: &'a Program<I>) -> Self {
+        Debugger {
+            vm: Vm::new(program),
+            breakpoints: vec![],
+            watches: vec![],
+            trace: vec![],
+        }
+    }
+
+    pub fn vm(&self) -> &Vm<'a, I> {
+        &self.vm
+    }
+
+    fn register_index(name: &str) -> Result<usize> {
+        I::REGISTERS
+            .iter()
+            .position(|&r| r == name)
+            .ok_or_else(|| anyhow!("no register `{}`", name))
+    }
+
+    fn hit_breakpoint(&self) -> Option<usize> {
+        let state = self.vm.state();
+        self.breakpoints.iter().position(|b| match b {
+            Breakpoint::Pc(pc) => state.pc == *pc as i64,
+            Breakpoint::Register {
+                name,
+                comparison,
+                value,
+            } => self
+                .vm
+                .register(name)
+                .is_some_and(|r| comparison.holds(r, *value)),
+        })
+    }
+
+    fn describe(entry: &TraceEntry<I>) -> String {
+        let registers = I::REGISTERS
+            .iter()
+            .zip(&entry.registers)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>();
+        format!(
+            "#{} pc {}: {}  {}",
+            entry.step,
+            entry.pc,
+            entry.instruction,
+            registers.join(" ")
+        )
+    }
+
+    fn describe_result(result: &RunResult<I>) -> String {
+        match result {
+            RunResult::Terminated { steps } => format!("terminated after {} steps", steps),
+            RunResult::Looped { pc, steps } => {
+                format!("looped: pc {} would run again after {} steps", pc, steps)
+            }
+            RunResult::OutOfBounds {
+                pc,
+                instruction,
+                target,
+                steps,
+            } => format!(
+                "out of bounds: `{}` at pc {} jumped to {} after {} steps",
+                instruction, pc, target, steps
+            ),
+            RunResult::StepLimit { steps } => format!("stopped at the step limit of {}", steps),
+        }
+    }
+
+    // runs one instruction, recording it and reporting changes to watched registers. returns
+    // the result once the program stops
+    fn step<W: Write>(&mut self, out: &mut W) -> Result<Option<RunResult<I>>> {
+        let before = self.vm.state().clone();
+        let steps = self.vm.steps();
+        let pc = before.pc as usize;
+        let instruction = self.vm.program().get(before.pc).cloned();
+        let result = self.vm.step();
+        // nothing runs once the program has stopped
+        if self.vm.steps() > steps {
+            let entry = TraceEntry {
+                step: self.vm.steps(),
+                pc,
+                instruction: instruction.expect("an instruction ran"),
+                registers: self.vm.state().registers.clone(),
+            };
+            writeln!(out, "{}", Debugger::describe(&entry))?;
+            for name in &self.watches {
+                let index = Debugger::<I>::register_index(name)?;
+                let (old, new) = (before.registers[index], entry.registers[index]);
+                if old != new {
+                    writeln!(out, "watch: {} {} -> {}", name, old, new)?;
+                }
+            }
+            self.trace.push(entry);
+        }
+        if let Some(result) = &result {
+            writeln!(out, "{}", Debugger::describe_result(result))?;
+        }
+        Ok(result)
+    }
+
+    fn continue_running<W: Write>(&mut self, out: &mut W) -> Result<()> {
+        // always take one step, so continuing from a breakpoint gets past it
+        loop {
+            if self.step(out)?.is_some() {
+                return Ok(());
+            }
+            if let Some(index) = self.hit_breakpoint() {
+                writeln!(out, "breakpoint {}: {}", index, self.breakpoints[index])?;
+                return Ok(());
+            }
+        }
+    }
+
+    fn show_loop<W: Write>(&self, out: &mut W) -> Result<()> {
+        match self.vm.result() {
+            Some(RunResult::Looped { pc, steps }) => {
+                let first = self
+                    .trace
+                    .iter()
+                    .find(|e| e.pc == *pc)
+                    .expect("a looping pc has run before");
+                let last = self.trace.last().expect("a loop takes steps");
+                writeln!(
+                    out,
+                    "loop entered at pc {} (`{}`), first run at step {}",
+                    pc, first.instruction, first.step
+                )?;
+                writeln!(
+                    out,
+                    "pc {} (`{}`) leads back to it, so every {} steps repeat from step {}",
+                    last.pc,
+                    last.instruction,
+                    steps + 1 - first.step,
+                    first.step
+                )?;
+            }
+            _ => writeln!(out, "not looping (yet)")?,
+        }
+        Ok(())
+    }
+
+    // carries out one command, returning false to stop debugging
+    pub fn execute<W: Write>(&mut self, command: Command, out: &mut W) -> Result<bool> {
+        match command {
+            Command::Step(n) => {
+                for _ in 0..n {
+                    if self.step(out)?.is_some() {
+                        break;
+                    }
+                }
+            }
+            Command::Continue => self.continue_running(out)?,
+            Command::Break(breakpoint) => {
+                if let Breakpoint::Register { name, .. } = &breakpoint {
+                    Debugger::<I>::register_index(name)?;
+                }
+                writeln!(out, "breakpoint {}: {}", self.breakpoints.len(), breakpoint)?;
+                self.breakpoints.push(breakpoint);
+            }
+            Command::Delete(index) => {
+                if index >= self.breakpoints.len() {
+                    bail!("no breakpoint {}", index);
+                }
+                self.breakpoints.remove(index);
+            }
+            Command::Breakpoints => {
+                for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+                    writeln!(out, "breakpoint {}: {}", i, breakpoint)?;
+                }
+            }
+            Command::Watch(name) => {
+                Debugger::<I>::register_index(&name)?;
+                if !self.watches.contains(&name) {
+                    self.watches.push(name);
+                }
+            }
+            Command::Unwatch(name) => self.watches.retain(|w| *w != name),
+            Command::Trace(n) => {
+                let skip = n.map_or(0, |n| self.trace.len().saturating_sub(n));
+                for entry in &self.trace[skip..] {
+                    writeln!(out, "{}", Debugger::describe(entry))?;
+                }
+            }
+            Command::Loop => self.show_loop(out)?,
+            Command::Print => {
+                let state = self.vm.state();
+                write!(out, "pc {}", state.pc)?;
+                for (name, value) in I::REGISTERS.iter().zip(&state.registers) {
+                    write!(out, " {}={}", name, value)?;
+                }
+                writeln!(out, " steps={}", self.vm.steps())?;
+                if let Some(result) = self.vm.result() {
+                    writeln!(out, "{}", Debugger::describe_result(result))?;
+                }
+            }
+            Command::Help => writeln!(out, "{}", HELP)?,
+            Command::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // reads commands a line at a time until `quit` or the end of the input. mistakes are
+    // reported without ending the session
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut out: W) -> Result<()> {
+        write!(out, "(debug) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                let keep_going = line
+                    .parse()
+                    .and_then(|command| self.execute(command, &mut out));
+                match keep_going {
+                    Ok(false) => return Ok(()),
+                    Ok(true) => {}
+                    Err(e) => writeln!(out, "error: {}", e)?,
+                }
+            }
+            write!(out, "(debug) ")?;
+            out.flush()?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::Instruction;
+
+    const INPUT: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+    fn session(program: &str, commands: &str) -> Result<String> {
+        let program: Program<Instruction> = program.parse()?;
+        let mut debugger = Debugger::new(&program);
+        let mut out = vec![];
+        debugger.repl(commands.as_bytes(), &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_parse_command() -> Result<()> {
+        assert_eq!("step".parse::<Command>()?, Command::Step(1));
+        assert_eq!("s 3".parse::<Command>()?, Command::Step(3));
+        assert_eq!(
+            "break acc >= -2".parse::<Command>()?,
+            Command::Break(Breakpoint::Register {
+                name: "acc".into(),
+                comparison: Comparison::Ge,
+                value: -2
+            })
+        );
+        assert_eq!("b 4".parse::<Command>()?, Command::Break(Breakpoint::Pc(4)));
+        assert!("break acc ~ 3".parse::<Command>().is_err());
+        assert!("jump".parse::<Command>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_and_watch() -> Result<()> {
+        let out = session(INPUT, "watch acc\nstep 2\nprint\nquit\nstep")?;
+        assert_eq!(
+            out,
+            "(debug) (debug) #1 pc 0: nop +0  acc=0
+#2 pc 1: acc +1  acc=1
+watch: acc 0 -> 1
+(debug) pc 2 acc=1 steps=2
+(debug) "
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_breakpoints() -> Result<()> {
+        let out = session(
+            INPUT,
+            "break 4\nbreak acc > 3\ncontinue\ncontinue\nbreakpoints",
+        )?;
+        assert_eq!(
+            out,
+            "(debug) breakpoint 0: pc 4
+(debug) breakpoint 1: acc > 3
+(debug) #1 pc 0: nop +0  acc=0
+#2 pc 1: acc +1  acc=1
+#3 pc 2: jmp +4  acc=1
+#4 pc 6: acc +1  acc=2
+#5 pc 7: jmp -4  acc=2
+#6 pc 3: acc +3  acc=5
+breakpoint 0: pc 4
+(debug) #7 pc 4: jmp -3  acc=5
+looped: pc 1 would run again after 7 steps
+(debug) breakpoint 0: pc 4
+breakpoint 1: acc > 3
+(debug) \n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_loop_and_trace() -> Result<()> {
+        let out = session(INPUT, "loop\ncontinue\nloop\ntrace 2")?;
+        let expected_loop = "loop entered at pc 1 (`acc +1`), first run at step 2
+pc 4 (`jmp -3`) leads back to it, so every 6 steps repeat from step 2
+";
+        assert!(out.starts_with("(debug) not looping (yet)\n"));
+        assert!(out.contains(expected_loop));
+        assert!(out.ends_with("(debug) #6 pc 3: acc +3  acc=5\n#7 pc 4: jmp -3  acc=5\n(debug) \n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() -> Result<()> {
+        let out = session("jmp -1", "watch x\ndelete 0\nstep\nstep")?;
+        assert_eq!(
+            out,
+            "(debug) error: no register `x`
+(debug) error: no breakpoint 0
+(debug) #1 pc 0: jmp -1  acc=0
+out of bounds: `jmp -1` at pc 0 jumped to -1 after 1 steps
+(debug) out of bounds: `jmp -1` at pc 0 jumped to -1 after 1 steps
+(debug) \n"
+        );
+        Ok(())
+    }
+}
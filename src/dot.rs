@@ -0,0 +1,210 @@
+use crate::shared::{Opt, Options};
+use std::fmt;
+
+// for days whose puzzle is a graph
+pub const DOT: Opt = Opt {
+    name: "dot",
+    help: "Print the puzzle's graph in Graphviz DOT format instead of the answer",
+    value: None,
+};
+
+pub fn wanted(options: &Options) -> bool {
+    options.flag(DOT.name)
+}
+
+// double quoted, so any id or label is allowed
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn attributes(attrs: &[(&'static str, String)]) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let attrs = attrs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, quote(value)))
+        .collect::<Vec<_>>();
+    format!(" [{}]", attrs.join(", "))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    id: String,
+    attrs: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    from: String,
+    to: String,
+    attrs: Vec<(&'static str, String)>,
+}
+
+impl Edge {
+    pub fn new(from: impl ToString, to: impl ToString) -> Self {
+        Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            attrs: vec![],
+        }
+    }
+
+    pub fn label(mut self, label: impl ToString) -> Self {
+        self.attrs.push(("label", label.to_string()));
+        self
+    }
+
+    // heavier edges are kept shorter and straighter
+    pub fn weight(mut self, weight: usize) -> Self {
+        self.attrs.push(("weight", weight.to_string()));
+        self
+    }
+
+    // e.g. `dashed` or `bold`
+    pub fn style(mut self, style: &str) -> Self {
+        self.attrs.push(("style", style.into()));
+        self
+    }
+}
+
+// nodes drawn together in a labelled box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    label: String,
+    nodes: Vec<Node>,
+}
+
+impl Cluster {
+    pub fn new(label: impl ToString) -> Self {
+        Cluster {
+            label: label.to_string(),
+            nodes: vec![],
+        }
+    }
+
+    pub fn add_node(&mut self, id: impl ToString, label: impl ToString) {
+        self.nodes.push(Node {
+            id: id.to_string(),
+            attrs: vec![("label", label.to_string())],
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    name: String,
+    directed: bool,
+    nodes: Vec<Node>,
+    clusters: Vec<Cluster>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn directed(name: &str) -> Self {
+        Graph::new(name, true)
+    }
+
+    pub fn undirected(name: &str) -> Self {
+        Graph::new(name, false)
+    }
+
+    fn new(name: &str, directed: bool) -> Self {
+        Graph {
+            name: name.into(),
+            directed,
+            nodes: vec![],
+            clusters: vec![],
+            edges: vec![],
+        }
+    }
+
+    pub fn add_node(&mut self, id: impl ToString, label: impl ToString) {
+        self.nodes.push(Node {
+            id: id.to_string(),
+            attrs: vec![("label", label.to_string())],
+        });
+    }
+
+    pub fn add_cluster(&mut self, cluster: Cluster) {
+        self.clusters.push(cluster);
+    }
+
+    pub fn add_edge(&mut self, edge: Edge) {
+        self.edges.push(edge);
+    }
+}
+
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (keyword, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        writeln!(f, "{} {} {{", keyword, quote(&self.name))?;
+        for node in &self.nodes {
+            writeln!(f, "    {}{};", quote(&node.id), attributes(&node.attrs))?;
+        }
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            // graphviz only draws subgraphs named `cluster...` as boxes
+            writeln!(f, "    subgraph cluster_{} {{", i)?;
+            writeln!(f, "        label={};", quote(&cluster.label))?;
+            for node in &cluster.nodes {
+                writeln!(f, "        {}{};", quote(&node.id), attributes(&node.attrs))?;
+            }
+            writeln!(f, "    }}")?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                f,
+                "    {} {} {}{};",
+                quote(&edge.from),
+                arrow,
+                quote(&edge.to),
+                attributes(&edge.attrs)
+            )?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directed() {
+        let mut graph = Graph::directed("bags");
+        graph.add_node("a", "say \"a\"");
+        let mut cluster = Cluster::new("pair");
+        cluster.add_node(1, "one");
+        cluster.add_node(2, "two");
+        graph.add_cluster(cluster);
+        graph.add_edge(Edge::new("a", 1).label(3).weight(3));
+        graph.add_edge(Edge::new(1, 2).style("dashed"));
+        assert_eq!(
+            graph.to_string(),
+            r#"digraph "bags" {
+    "a" [label="say \"a\""];
+    subgraph cluster_0 {
+        label="pair";
+        "1" [label="one"];
+        "2" [label="two"];
+    }
+    "a" -> "1" [label="3", weight="3"];
+    "1" -> "2" [style="dashed"];
+}"#
+        );
+    }
+
+    #[test]
+    fn test_undirected() {
+        let mut graph = Graph::undirected("tiles");
+        graph.add_edge(Edge::new(1, 2));
+        assert_eq!(
+            graph.to_string(),
+            "graph \"tiles\" {\n    \"1\" -- \"2\";\n}"
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use crate::coor::Coor;
+use crate::symmetry::{Edge, D4};
+use anyhow::{bail, Error, Result};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+const ORTHOGONAL: [Coor; 4] = [
+    Coor::new(0, -1),
+    Coor::new(1, 0),
+    Coor::new(0, 1),
+    Coor::new(-1, 0),
+];
+
+const ADJACENT: [Coor; 8] = [
+    Coor::new(-1, -1),
+    Coor::new(0, -1),
+    Coor::new(1, -1),
+    Coor::new(-1, 0),
+    Coor::new(1, 0),
+    Coor::new(-1, 1),
+    Coor::new(0, 1),
+    Coor::new(1, 1),
+];
+
+impl Connectivity {
+    pub fn deltas(&self) -> &'static [Coor] {
+        match self {
+            Connectivity::Four => &ORTHOGONAL,
+            Connectivity::Eight => &ADJACENT,
+        }
+    }
+}
+
+// dense, row-major grid with (0, 0) in the top left corner
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+}
+
+impl<T> Grid<T> {
+    pub fn from_cells(width: usize, cells: Vec<T>) -> Result<Self> {
+        if width == 0 && !cells.is_empty() || width > 0 && !cells.len().is_multiple_of(width) {
+            bail!(
+                "{} cells don't fit into rows of width {}",
+                cells.len(),
+                width
+            );
+        }
+        Ok(Grid { cells, width })
+    }
+
+    pub fn from_fn<F>(width: usize, height: usize, f: F) -> Self
+    where
+        F: FnMut(Coor) -> T,
+    {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Coor::new(x as i64, y as i64)))
+            .map(f)
+            .collect();
+        Grid { cells, width }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    pub fn contains(&self, coor: &Coor) -> bool {
+        coor.x >= 0 && coor.y >= 0 && coor.x < self.width as i64 && coor.y < self.height() as i64
+    }
+
+    pub fn idx(&self, coor: &Coor) -> Option<usize> {
+        if !self.contains(coor) {
+            return None;
+        }
+        Some((coor.y as usize) * self.width + (coor.x as usize))
+    }
+
+    pub fn coor(&self, idx: usize) -> Coor {
+        Coor::new((idx % self.width) as i64, (idx / self.width) as i64)
+    }
+
+    pub fn get(&self, coor: &Coor) -> Option<&T> {
+        self.idx(coor).map(|idx| &self.cells[idx])
+    }
+
+    pub fn get_mut(&mut self, coor: &Coor) -> Option<&mut T> {
+        let idx = self.idx(coor)?;
+        Some(&mut self.cells[idx])
+    }
+
+    // treat the grid as a torus, i.e. coordinates outside wrap around to the other side
+    pub fn get_wrapping(&self, coor: &Coor) -> Option<&T> {
+        if self.cells.is_empty() {
+            return None;
+        }
+        let wrapped = Coor::new(
+            coor.x.rem_euclid(self.width as i64),
+            coor.y.rem_euclid(self.height() as i64),
+        );
+        self.get(&wrapped)
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn into_cells(self) -> Vec<T> {
+        self.cells
+    }
+
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        if y >= self.height() {
+            return None;
+        }
+        Some(&self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // `chunks` panics on a zero chunk size
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        let width = self.width;
+        let start = if x < width { x } else { self.cells.len() };
+        self.cells[start..].iter().step_by(width.max(1))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Coor, &T)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(idx, t)| (Coor::new((idx % width) as i64, (idx / width) as i64), t))
+    }
+
+    pub fn coors(&self) -> impl Iterator<Item = Coor> {
+        let width = self.width;
+        (0..self.cells.len()).map(move |idx| Coor::new((idx % width) as i64, (idx / width) as i64))
+    }
+
+    pub fn neighbours<'a>(
+        &'a self,
+        coor: &Coor,
+        connectivity: Connectivity,
+    ) -> impl Iterator<Item = Coor> + 'a {
+        let coor = *coor;
+        connectivity
+            .deltas()
+            .iter()
+            .map(move |&delta| coor + delta)
+            .filter(move |n| self.contains(n))
+    }
+
+    // the cells visited walking from `coor` (exclusive) in steps of `direction` until
+    // leaving the grid
+    pub fn ray<'a>(
+        &'a self,
+        coor: &Coor,
+        direction: Coor,
+    ) -> impl Iterator<Item = (Coor, &'a T)> + 'a {
+        let mut pos = *coor;
+        std::iter::from_fn(move || {
+            pos += direction;
+            self.get(&pos).map(|t| (pos, t))
+        })
+    }
+
+    pub fn map<U, F>(&self, f: F) -> Grid<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        Grid {
+            cells: self.cells.iter().map(f).collect(),
+            width: self.width,
+        }
+    }
+
+    pub fn map_with_coor<U, F>(&self, mut f: F) -> Grid<U>
+    where
+        F: FnMut(Coor, &T) -> U,
+    {
+        Grid {
+            cells: self.iter().map(|(coor, t)| f(coor, t)).collect(),
+            width: self.width,
+        }
+    }
+
+    pub fn count<F>(&self, f: F) -> usize
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.cells.iter().filter(|t| f(t)).count()
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn transformed(&self, symmetry: D4) -> Grid<T> {
+        let (width, height) = symmetry.dimensions(self.width, self.height());
+        let inverse = symmetry.inverse();
+        Grid::from_fn(width, height, |coor| {
+            self[inverse.apply_in_box(coor, width, height)].clone()
+        })
+    }
+
+    // in the direction of increasing coordinates
+    pub fn edge(&self, edge: Edge) -> Vec<T> {
+        let last_row = self.height().saturating_sub(1);
+        let last_column = self.width.saturating_sub(1);
+        match edge {
+            Edge::Top => self.row(0).unwrap_or(&[]).to_vec(),
+            Edge::Bottom => self.row(last_row).unwrap_or(&[]).to_vec(),
+            Edge::Left => self.column(0).cloned().collect(),
+            Edge::Right => self.column(last_column).cloned().collect(),
+        }
+    }
+}
+
+impl<T> Grid<T>
+where
+    T: TryFrom<char>,
+    <T as TryFrom<char>>::Error: Into<Error>,
+{
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut cells = vec![];
+        let mut width = None;
+        for line in input.lines() {
+            let before = cells.len();
+            for c in line.chars() {
+                cells.push(T::try_from(c).map_err(|e| e.into())?);
+            }
+            let line_width = cells.len() - before;
+            match width {
+                None => width = Some(line_width),
+                Some(w) if w != line_width => {
+                    bail!("ragged grid: row of width {} (expected {})", line_width, w)
+                }
+                _ => {}
+            }
+        }
+        Ok(Grid {
+            cells,
+            width: width.unwrap_or(0),
+        })
+    }
+}
+
+impl<T> Index<Coor> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, coor: Coor) -> &T {
+        self.get(&coor)
+            .unwrap_or_else(|| panic!("{:?} outside grid", coor))
+    }
+}
+
+impl<T> IndexMut<Coor> for Grid<T> {
+    fn index_mut(&mut self, coor: Coor) -> &mut T {
+        self.get_mut(&coor)
+            .unwrap_or_else(|| panic!("{:?} outside grid", coor))
+    }
+}
+
+impl<T> fmt::Display for Grid<T>
+where
+    T: Copy + Into<char>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for &t in row {
+                write!(f, "{}", t.into())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "#..
+.#.
+..#
+#.#";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(INPUT)?;
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 4);
+        assert_eq!(grid[Coor::new(1, 1)], '#');
+        assert_eq!(grid.get(&Coor::new(3, 0)), None);
+        assert_eq!(grid.get(&Coor::new(0, -1)), None);
+        assert!(Grid::<char>::parse("..\n...").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_round_trip() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(INPUT)?;
+        assert_eq!(grid.to_string(), INPUT);
+        Ok(())
+    }
+
+    #[test]
+    fn test_coor() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(INPUT)?;
+        for idx in 0..12 {
+            assert_eq!(grid.idx(&grid.coor(idx)), Some(idx));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(INPUT)?;
+        assert_eq!(grid.get_wrapping(&Coor::new(4, 1)), Some(&'#'));
+        assert_eq!(grid.get_wrapping(&Coor::new(-1, -2)), Some(&'#'));
+        assert_eq!(grid.get_wrapping(&Coor::new(-2, 4)), Some(&'.'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rows_and_columns() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(INPUT)?;
+        assert_eq!(grid.row(3), Some(&['#', '.', '#'][..]));
+        assert_eq!(grid.row(4), None);
+        assert_eq!(grid.rows().count(), 4);
+        assert_eq!(grid.column(2).collect::<String>(), "..##");
+        assert_eq!(grid.column(3).count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_neighbours() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(INPUT)?;
+        assert_eq!(
+            grid.neighbours(&Coor::new(0, 0), Connectivity::Four)
+                .count(),
+            2
+        );
+        assert_eq!(
+            grid.neighbours(&Coor::new(0, 0), Connectivity::Eight)
+                .count(),
+            3
+        );
+        assert_eq!(
+            grid.neighbours(&Coor::new(1, 1), Connectivity::Eight)
+                .count(),
+            8
+        );
+        let diagonal = grid
+            .ray(&Coor::new(0, 0), Coor::new(1, 1))
+            .map(|(_, &c)| c)
+            .collect::<String>();
+        assert_eq!(diagonal, "##");
+        Ok(())
+    }
+}
@@ -1,5 +1,21 @@
 // pub mod cpu;
+pub mod assembler;
+pub mod automaton;
+pub mod big_int;
+pub mod bit_grid;
 pub mod coor;
+pub mod cycle;
+pub mod debugger;
+pub mod dot;
+pub mod grid;
 pub mod mod_arith;
+pub mod mod_int;
+pub mod number_theory;
+pub mod pattern;
+pub mod search;
 pub mod shared;
+pub mod sparse_grid;
+pub mod symmetry;
+pub mod visualise;
+pub mod vm;
 pub use crate::shared::dispatch;
@@ -1,43 +1,462 @@
-fn gcd_extended(a: i64, b: i64) -> (i64, i64, i64) {
-    // (b, x, y)
+use crate::big_int::BigInt;
+use crate::number_theory::{factorise, multiplicative_order};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 
-    if a == 0 {
-        return (b, 0, 1);
+// the integer types modular arithmetic works on, signed or not
+pub trait Integral:
+    Copy
+    + Ord
+    + Debug
+    + Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+
+    // a * b % m for a and b in 0..m, without overflowing
+    fn mul_rem(a: Self, b: Self, m: Self) -> Self;
+
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+// the signed ones, which inverses and the crt need
+pub trait Int: Integral + Neg<Output = Self> {}
+
+impl<T: Integral + Neg<Output = T>> Int for T {}
+
+// widening to a type big enough for any product
+macro_rules! impl_integral {
+    ($($t:ty => $wide:ty),*) => {
+        $(
+            impl Integral for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const TWO: Self = 2;
+
+                fn mul_rem(a: Self, b: Self, m: Self) -> Self {
+                    (a as $wide * b as $wide % m as $wide) as Self
+                }
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_integral!(i8 => i16, i16 => i32, i32 => i64, i64 => i128, isize => i128, u64 => u128);
+
+// nothing is wider, so double and add instead. a + a can't overflow as unsigned
+impl Integral for i128 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const TWO: Self = 2;
+
+    fn mul_rem(a: Self, b: Self, m: Self) -> Self {
+        let (mut a, mut b, m) = (a as u128, b as u128, m as u128);
+        let mut res = 0;
+        while b > 0 {
+            if b & 1 == 1 {
+                res = (res + a) % m;
+            }
+            a = (a + a) % m;
+            b >>= 1;
+        }
+        res as Self
+    }
+
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        i128::checked_mul(self, other)
+    }
+}
+
+// (g, x, y) with a * x + b * y == g, where g is the non-negative gcd of a and b
+pub fn gcd_extended<T: Int>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::ONE, T::ZERO);
+    let (mut old_y, mut y) = (T::ZERO, T::ONE);
+    while r != T::ZERO {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < T::ZERO {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
     }
+}
+
+// the representative of `a` in 0..m
+pub fn modulo<T: Integral>(a: T, m: T) -> T {
+    let r = a % m;
+    if r < T::ZERO {
+        r + m
+    } else {
+        r
+    }
+}
+
+// `None` unless `a` and `m` are coprime
+pub fn mod_inverse<T: Int>(a: T, m: T) -> Option<T> {
+    if m <= T::ZERO {
+        return None;
+    }
+    let (g, x, _) = gcd_extended(modulo(a, m), m);
+    if g != T::ONE {
+        return None;
+    }
+    Some(modulo(x, m))
+}
 
-    let (g, x, y) = gcd_extended(b % a, a);
+// `None` unless `b` has an inverse mod `m`
+pub fn mod_div<T: Int>(a: T, b: T, m: T) -> Option<T> {
+    mod_inverse(b, m).map(|inverse| mod_mul(a, inverse, m))
+}
 
-    return (g, y - (b / a) * x, x);
+pub fn mod_mul<T: Integral>(a: T, b: T, m: T) -> T {
+    T::mul_rem(modulo(a, m), modulo(b, m), m)
 }
 
-fn mod_inverse(a: i64, m: i64) -> i64 {
-    let (g, x, _) = gcd_extended(a, m);
-    if g != 1 {
-        unimplemented!("inverse doesn't exist");
+// base^exp mod m by square and multiply
+pub fn mod_pow<T: Integral>(base: T, mut exp: u64, m: T) -> T {
+    let mut res = modulo(T::ONE, m);
+    let mut base = modulo(base, m);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = T::mul_rem(res, base, m);
+        }
+        base = T::mul_rem(base, base, m);
+        exp >>= 1;
     }
-    // m is added to handle negative x
-    (x % m + m) % m
+    res
 }
 
-pub fn mod_div(a: i64, b: i64, m: i64) -> i64 {
-    mod_mul(a, mod_inverse(b, m), m)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CrtError {
+    // no number satisfies every congruence
+    Inconsistent,
+    // the lcm of the moduli doesn't fit
+    Overflow,
+    NonPositiveModulus,
 }
 
-pub fn mod_mul(a: i64, b: i64, m: i64) -> i64 {
-    let mut res = 0;
-    let mut a = a % m;
-    let mut b = b;
-    while b > 0 {
-        // If b is odd, add 'a' to result
-        if b % 2 == 1 {
-            res = (res + a) % m;
+// the smallest non-negative x with x == residue mod modulus for every pair, and the lcm of the
+// moduli; every solution is x plus a multiple of the lcm. moduli needn't be coprime
+pub fn try_crt<T: Int>(congruences: &[(T, T)]) -> Result<(T, T), CrtError> {
+    let mut res = (T::ZERO, T::ONE);
+    for &(residue, modulus) in congruences {
+        if modulus <= T::ZERO {
+            return Err(CrtError::NonPositiveModulus);
         }
+        let (x, m) = res;
+        let residue = modulo(residue, modulus);
+        // x + m * t == residue mod modulus, so m * t == residue - x, which is solvable iff the
+        // gcd divides the right hand side
+        let (g, p, _) = gcd_extended(m, modulus);
+        let diff = residue - modulo(x, modulus);
+        if diff % g != T::ZERO {
+            return Err(CrtError::Inconsistent);
+        }
+        let step = modulus / g;
+        let t = mod_mul(diff / g, p, step);
+        let lcm = m.checked_mul(step).ok_or(CrtError::Overflow)?;
+        // t < step, so this stays below the lcm
+        res = (x + m * t, lcm);
+    }
+    Ok(res)
+}
+
+// like `try_crt`, without saying what went wrong
+pub fn crt<T: Int>(congruences: &[(T, T)]) -> Option<(T, T)> {
+    try_crt(congruences).ok()
+}
 
-        // Multiply 'a' with 2
-        a = (a * 2) % m;
-        // Divide b by 2
-        b /= 2;
+// `try_crt` on i64 congruences with the solution and lcm worked out as `BigInt`s, so it can't
+// overflow
+pub fn try_crt_big(congruences: &[(i64, i64)]) -> Result<(BigInt, BigInt), CrtError> {
+    let (mut x, mut m) = (BigInt::zero(), BigInt::from(1));
+    for &(residue, modulus) in congruences {
+        if modulus <= 0 {
+            return Err(CrtError::NonPositiveModulus);
+        }
+        let residue = modulo(residue, modulus);
+        // the same as `try_crt`, with x and m reduced mod the modulus first so they fit
+        let small = |n: &BigInt| {
+            (n % &BigInt::from(modulus))
+                .to_i64()
+                .expect("below the modulus")
+        };
+        let (g, p, _) = gcd_extended(small(&m), modulus);
+        let diff = residue - small(&x);
+        if diff % g != 0 {
+            return Err(CrtError::Inconsistent);
+        }
+        let step = modulus / g;
+        let t = mod_mul(diff / g, p, step);
+        x = &x + &(&m * &BigInt::from(t));
+        m = &m * &BigInt::from(step);
     }
+    Ok((x, m))
+}
 
-    res % m
+// the smallest x in 0..n with a^x == b mod m, for `a` coprime to `m`. takes O(sqrt(n)) steps
+fn baby_step_giant_step(a: i64, b: i64, m: i64, n: i64) -> Option<i64> {
+    let steps = (n as f64).sqrt().ceil() as i64;
+    let mut baby_steps = HashMap::new();
+    let mut power = 1;
+    for j in 0..steps {
+        baby_steps.entry(power).or_insert(j);
+        power = mod_mul(power, a, m);
+    }
+    // a^-steps
+    let giant_step = mod_inverse(power, m)?;
+    let mut gamma = modulo(b, m);
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&gamma) {
+            let x = i * steps + j;
+            return if x < n { Some(x) } else { None };
+        }
+        gamma = mod_mul(gamma, giant_step, m);
+    }
+    None
+}
+
+// x mod ord(a) with a^x == b, solving in each prime power subgroup of the order separately
+// (Pohlig-Hellman) and combining the results
+fn pohlig_hellman(a: i64, b: i64, m: i64) -> Option<i64> {
+    let n = multiplicative_order(a as u64, m as u64)? as i64;
+    let mut congruences = vec![];
+    for (q, e) in factorise(n as u64) {
+        let q = q as i64;
+        let q_e = q.pow(e);
+        // moved into the subgroup of order q^e
+        let a_i = mod_pow(a, (n / q_e) as u64, m);
+        let b_i = mod_pow(b, (n / q_e) as u64, m);
+        // generates the subgroup of order q
+        let gamma = mod_pow(a_i, q.pow(e - 1) as u64, m);
+        let mut x = 0;
+        for k in 0..e {
+            // strip the digits found so far and move into the subgroup of order q
+            let rest = mod_mul(mod_inverse(mod_pow(a_i, x as u64, m), m)?, b_i, m);
+            let h = mod_pow(rest, q.pow(e - 1 - k) as u64, m);
+            let digit = baby_step_giant_step(gamma, h, m, q)?;
+            x += digit * q.pow(k);
+        }
+        congruences.push((x, q_e));
+    }
+    let (x, _) = crt(&congruences)?;
+    Some(x)
+}
+
+// the smallest x >= 0 with base^x == target mod modulus, if any
+pub fn discrete_log(base: i64, target: i64, modulus: i64) -> Option<i64> {
+    if modulus <= 0 {
+        return None;
+    }
+    let mut m = modulus;
+    let a = modulo(base, m);
+    let mut b = modulo(target, m);
+    // while a shares factors with m, peel off one power of a at a time: a^x == b becomes
+    // (a / g) * a^(x - 1) == b / g mod m / g
+    let mut k = 0;
+    let mut coefficient = modulo(1, m);
+    loop {
+        if b == coefficient {
+            return Some(k);
+        }
+        let g = gcd_extended(a, m).0;
+        if g == 1 {
+            break;
+        }
+        if b % g != 0 {
+            return None;
+        }
+        b /= g;
+        m /= g;
+        k += 1;
+        coefficient = mod_mul(coefficient, a / g, m);
+    }
+    // now a is invertible, so coefficient * a^x == b can be solved for a^x
+    let b = mod_div(b, coefficient, m)?;
+    let x = pohlig_hellman(a, b, m)?;
+    // the subgroups only see b's components in the group generated by a
+    if mod_pow(a, x as u64, m) == b {
+        Some(x + k)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_extended() {
+        for &(a, b) in &[
+            (240i64, 46),
+            (46, 240),
+            (-240, 46),
+            (240, -46),
+            (0, 7),
+            (7, 0),
+        ] {
+            let (g, x, y) = gcd_extended(a, b);
+            assert_eq!(a * x + b * y, g);
+            assert!(g >= 0);
+        }
+        assert_eq!(gcd_extended(240i32, 46).0, 2);
+        assert_eq!(gcd_extended(0i128, 0).0, 0);
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3i64, 11), Some(4));
+        assert_eq!(mod_inverse(-3i64, 11), Some(7));
+        assert_eq!(mod_inverse(6i32, 9), None);
+        assert_eq!(mod_inverse(5i64, 0), None);
+        assert_eq!(mod_inverse(5i64, 1), Some(0));
+        let m: i128 = 1_000_000_000_000_000_000_000_007;
+        let inverse = mod_inverse(123_456_789i128, m).expect("m is prime");
+        assert_eq!(mod_mul(inverse, 123_456_789, m), 1);
+    }
+
+    #[test]
+    fn test_mod_div() {
+        assert_eq!(mod_div(8i64, 3, 11), Some(10));
+        assert_eq!(mod_div(-8i64, 3, 11), Some(1));
+        assert_eq!(mod_div(8i16, 3, 12), None);
+    }
+
+    #[test]
+    fn test_mod_mul() {
+        assert_eq!(mod_mul(-3i64, 4, 7), 2);
+        assert_eq!(
+            mod_mul(123_456_789_012i64, 987_654_321_098, 1_000_000_007),
+            474_193_777
+        );
+        // products well beyond 64 bits
+        let m = (1i64 << 62) + 135;
+        assert_eq!(mod_mul(m - 1, m - 1, m), 1);
+        let m = (1i128 << 126) + 9;
+        assert_eq!(mod_mul(m - 1, m - 2, m), 2);
+        assert_eq!(mod_mul(120i8, 100, 127), 62);
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        assert_eq!(mod_pow(2i64, 10, 1000), 24);
+        assert_eq!(mod_pow(-2i64, 3, 7), 6);
+        assert_eq!(mod_pow(7i64, 0, 13), 1);
+        assert_eq!(mod_pow(7i64, 0, 1), 0);
+        // unsigned too, with moduli past i64::MAX
+        let m = u64::MAX - 58;
+        assert_eq!(mod_pow(2u64, 64, m), 59);
+        // Fermat's little theorem
+        let p = 1_000_000_007i64;
+        assert_eq!(mod_pow(123_456_789, (p - 1) as u64, p), 1);
+        let p = (1i64 << 61) - 1;
+        assert_eq!(mod_pow(3, (p - 1) as u64, p), 1);
+        for exp in 0..20 {
+            assert_eq!(mod_pow(3i32, exp, 1000), 3i64.pow(exp as u32) as i32 % 1000);
+        }
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt::<i64>(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(2i64, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(-1i64, 3), (-2, 5)]), Some((8, 15)));
+        // non-coprime moduli
+        assert_eq!(crt(&[(2i64, 6), (4, 8)]), Some((20, 24)));
+        assert_eq!(try_crt(&[(1i64, 6), (2, 8)]), Err(CrtError::Inconsistent));
+        assert_eq!(try_crt(&[(1i64, 0)]), Err(CrtError::NonPositiveModulus));
+        assert_eq!(
+            try_crt(&[(1i32, 65_521), (2, 65_519), (3, 65_497)]),
+            Err(CrtError::Overflow)
+        );
+        assert!(crt(&[(1i64, 65_521), (2, 65_519), (3, 65_497)]).is_some());
+        assert_eq!(try_crt_big(&[(1, 6), (2, 8)]), Err(CrtError::Inconsistent));
+        assert_eq!(
+            try_crt_big(&[(2, 6), (4, 8)]),
+            Ok((BigInt::from(20), BigInt::from(24)))
+        );
+        // the lcm is over 2^64
+        let primes = [4_294_967_291, 4_294_967_279, 4_294_967_231];
+        let congruences = [(-1, primes[0]), (7, primes[1]), (0, primes[2])];
+        assert_eq!(try_crt(&congruences), Err(CrtError::Overflow));
+        let (x, lcm) = try_crt_big(&congruences).unwrap();
+        assert_eq!(
+            lcm,
+            primes
+                .iter()
+                .map(|&p| BigInt::from(p))
+                .fold(BigInt::from(1), |a, b| a * b)
+        );
+        assert!(x < lcm);
+        for &(residue, modulus) in &congruences {
+            let residue = BigInt::from(modulo(residue, modulus));
+            assert_eq!(&x % &BigInt::from(modulus), residue);
+        }
+
+        // against brute force
+        for a in 1..12i64 {
+            for b in 1..12i64 {
+                for ra in -3..a {
+                    for rb in 0..b {
+                        let expected =
+                            (0..a * b).find(|x| (x - ra).rem_euclid(a) == 0 && (x - rb) % b == 0);
+                        let res = crt(&[(ra, a), (rb, b)]);
+                        assert_eq!(res.map(|(x, _)| x), expected);
+                        if let Some((_, lcm)) = res {
+                            assert_eq!(lcm, a * b / gcd_extended(a, b).0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_discrete_log() {
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11));
+        assert_eq!(discrete_log(3, 13, 17), Some(4));
+        assert_eq!(discrete_log(2, 3, 7), None);
+        assert_eq!(discrete_log(5, 1, 1), Some(0));
+        assert_eq!(discrete_log(5, 3, 0), None);
+
+        // against brute force, including bases sharing factors with the modulus
+        for m in 1..60i64 {
+            for a in 0..m {
+                let mut powers = vec![];
+                let mut power = 1 % m;
+                for _ in 0..2 * m {
+                    powers.push(power);
+                    power = power * a % m;
+                }
+                for b in 0..m {
+                    let expected = powers.iter().position(|&p| p == b).map(|x| x as i64);
+                    assert_eq!(
+                        discrete_log(a, b, m),
+                        expected,
+                        "{}^x == {} mod {}",
+                        a,
+                        b,
+                        m
+                    );
+                }
+            }
+        }
+    }
 }
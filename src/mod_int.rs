@@ -0,0 +1,250 @@
+use crate::mod_arith::{mod_inverse, mod_mul, mod_pow, modulo};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// an integer mod M, always kept in 0..M
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct ModInt<const M: i64>(i64);
+
+impl<const M: i64> ModInt<M> {
+    pub fn new(value: i64) -> Self {
+        ModInt(modulo(value, M))
+    }
+
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+
+    pub fn pow(&self, exp: u64) -> Self {
+        ModInt(mod_pow(self.0, exp, M))
+    }
+
+    // `None` unless the value is coprime to M
+    pub fn inv(&self) -> Option<Self> {
+        mod_inverse(self.0, M).map(ModInt)
+    }
+}
+
+impl<const M: i64> From<i64> for ModInt<M> {
+    fn from(value: i64) -> Self {
+        ModInt::new(value)
+    }
+}
+
+impl<const M: i64> Add for ModInt<M> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        // both are below M, so subtracting can't overflow where adding could
+        ModInt::new(self.0 - (M - other.0))
+    }
+}
+
+impl<const M: i64> Sub for ModInt<M> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        ModInt::new(self.0 - other.0)
+    }
+}
+
+impl<const M: i64> Mul for ModInt<M> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        ModInt(mod_mul(self.0, other.0, M))
+    }
+}
+
+// panics if `other` has no inverse, like integer division by zero
+impl<const M: i64> Div for ModInt<M> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        self * other.inv().expect("divisor isn't invertible")
+    }
+}
+
+impl<const M: i64> Neg for ModInt<M> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        ModInt::new(-self.0)
+    }
+}
+
+impl<const M: i64> AddAssign for ModInt<M> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<const M: i64> SubAssign for ModInt<M> {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl<const M: i64> MulAssign for ModInt<M> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<const M: i64> fmt::Display for ModInt<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// an integer mod a modulus only known at runtime. combining values with different moduli
+// panics
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct DynModInt {
+    value: i64,
+    modulus: i64,
+}
+
+impl DynModInt {
+    pub fn new(value: i64, modulus: i64) -> Self {
+        assert!(modulus > 0, "modulus must be positive");
+        DynModInt {
+            value: modulo(value, modulus),
+            modulus,
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    pub fn modulus(&self) -> i64 {
+        self.modulus
+    }
+
+    // another value with the same modulus
+    pub fn with_value(&self, value: i64) -> Self {
+        DynModInt::new(value, self.modulus)
+    }
+
+    pub fn pow(&self, exp: u64) -> Self {
+        self.with_value(mod_pow(self.value, exp, self.modulus))
+    }
+
+    pub fn inv(&self) -> Option<Self> {
+        mod_inverse(self.value, self.modulus).map(|value| self.with_value(value))
+    }
+
+    fn check(&self, other: &Self) {
+        assert_eq!(
+            self.modulus, other.modulus,
+            "can't combine values with different moduli"
+        );
+    }
+}
+
+impl Add for DynModInt {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.check(&other);
+        self.with_value(self.value - (self.modulus - other.value))
+    }
+}
+
+impl Sub for DynModInt {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.check(&other);
+        self.with_value(self.value - other.value)
+    }
+}
+
+impl Mul for DynModInt {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.check(&other);
+        self.with_value(mod_mul(self.value, other.value, self.modulus))
+    }
+}
+
+impl Div for DynModInt {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        self.check(&other);
+        self * other.inv().expect("divisor isn't invertible")
+    }
+}
+
+impl Neg for DynModInt {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.with_value(-self.value)
+    }
+}
+
+impl fmt::Display for DynModInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (mod {})", self.value, self.modulus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type M7 = ModInt<7>;
+
+    #[test]
+    fn test_mod_int() {
+        let a = M7::new(5);
+        let b = M7::new(-4);
+        assert_eq!(b.value(), 3);
+        assert_eq!(a + b, M7::new(1));
+        assert_eq!(a - b, M7::new(2));
+        assert_eq!(b - a, M7::new(5));
+        assert_eq!(a * b, M7::new(1));
+        assert_eq!(a / b, M7::new(4));
+        assert_eq!(-a, M7::new(2));
+        assert_eq!(a.pow(6), M7::new(1));
+        assert_eq!(a.inv(), Some(b));
+        assert_eq!(ModInt::<8>::new(4).inv(), None);
+        assert_eq!(a.to_string(), "5");
+
+        let mut c = a;
+        c += b;
+        c *= a;
+        c -= M7::from(1);
+        assert_eq!(c, M7::new(4));
+
+        // sums near the top of the range don't overflow
+        const BIG: i64 = i64::MAX - 24;
+        let big = ModInt::<BIG>::new(BIG - 1);
+        assert_eq!((big + big).value(), BIG - 2);
+        assert_eq!((big * big).value(), 1);
+    }
+
+    #[test]
+    fn test_dyn_mod_int() {
+        let a = DynModInt::new(5, 7);
+        let b = a.with_value(-4);
+        assert_eq!(a + b, a.with_value(1));
+        assert_eq!(a / b, a.with_value(4));
+        assert_eq!(-a, a.with_value(2));
+        assert_eq!(a.pow(3).value(), 6);
+        assert_eq!(a.to_string(), "5 (mod 7)");
+        assert_eq!(DynModInt::new(4, 8).inv(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "different moduli")]
+    fn test_mixed_moduli() {
+        let _ = DynModInt::new(1, 7) + DynModInt::new(1, 8);
+    }
+}
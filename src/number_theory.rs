@@ -0,0 +1,260 @@
+use crate::mod_arith::{mod_pow, Integral};
+
+// number theory on u64s; products are worked out in u128 so nothing overflows
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// `None` if it doesn't fit
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+pub fn gcd_of(numbers: &[u64]) -> u64 {
+    numbers.iter().fold(0, |acc, &n| gcd(acc, n))
+}
+
+// 1 for no numbers; `None` if it doesn't fit
+pub fn lcm_of(numbers: &[u64]) -> Option<u64> {
+    numbers.iter().try_fold(1, |acc, &n| lcm(acc, n))
+}
+
+// these bases are enough to make Miller-Rabin exact for every u64
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in &WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    // n - 1 == d * 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    WITNESSES.iter().all(|&a| {
+        let mut x = mod_pow(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = u64::mul_rem(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+// some non-trivial factor of an odd composite n, by Pollard's rho with Floyd's cycle finding
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x: u64| ((u64::mul_rem(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        // the sequence cycled without splitting n, so try another polynomial
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!("a composite always has a factor")
+}
+
+fn collect_prime_factors(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        factors.push(n);
+        return;
+    }
+    let d = pollard_rho(n);
+    collect_prime_factors(d, factors);
+    collect_prime_factors(n / d, factors);
+}
+
+// prime factors in increasing order, with their multiplicities
+pub fn factorise(mut n: u64) -> Vec<(u64, u32)> {
+    let mut primes = vec![];
+    // small factors are quicker to divide out, and rho needs an odd number
+    for p in 2..100 {
+        while n.is_multiple_of(p) {
+            primes.push(p);
+            n /= p;
+        }
+    }
+    if n > 1 {
+        collect_prime_factors(n, &mut primes);
+    }
+    primes.sort_unstable();
+    let mut res: Vec<(u64, u32)> = vec![];
+    for p in primes {
+        match res.last_mut() {
+            Some((last, k)) if *last == p => *k += 1,
+            _ => res.push((p, 1)),
+        }
+    }
+    res
+}
+
+// how many of 1..=n are coprime to n
+pub fn totient(n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    factorise(n)
+        .iter()
+        .fold(n, |acc, &(p, _)| acc / p * (p - 1))
+}
+
+// the smallest k > 0 with a^k == 1 mod m, if a and m are coprime
+pub fn multiplicative_order(a: u64, m: u64) -> Option<u64> {
+    if m == 0 || gcd(a, m) != 1 {
+        return None;
+    }
+    let mut res = totient(m);
+    for (p, _) in factorise(res) {
+        while res.is_multiple_of(p) && mod_pow(a, res / p, m) == 1 {
+            res /= p;
+        }
+    }
+    Some(res)
+}
+
+// the smallest generator of the multiplicative group mod m, which only exists for m = 1, 2, 4,
+// p^k or 2p^k with p an odd prime
+pub fn primitive_root(m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+    if m <= 2 {
+        return Some(m - 1);
+    }
+    let odd_part = m >> m.trailing_zeros();
+    let cyclic =
+        m == 4 || (m.trailing_zeros() <= 1 && odd_part > 1 && factorise(odd_part).len() == 1);
+    if !cyclic {
+        return None;
+    }
+    let phi = totient(m);
+    let prime_factors = factorise(phi);
+    (2..m).find(|&g| {
+        gcd(g, m) == 1
+            && prime_factors
+                .iter()
+                .all(|&(p, _)| mod_pow(g, phi / p, m) != 1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_prime() {
+        let limit = 10_000;
+        let mut sieve = vec![true; limit];
+        sieve[0] = false;
+        sieve[1] = false;
+        for i in 2..limit {
+            if sieve[i] {
+                for j in (i * i..limit).step_by(i) {
+                    sieve[j] = false;
+                }
+            }
+        }
+        for (n, &prime) in sieve.iter().enumerate() {
+            assert_eq!(is_prime(n as u64), prime, "{}", n);
+        }
+        assert!(is_prime(20201227));
+        assert!(is_prime((1 << 61) - 1));
+        assert!(is_prime(18_446_744_073_709_551_557));
+        // strong pseudoprimes to several small bases
+        assert!(!is_prime(3_215_031_751));
+        assert!(!is_prime(3_825_123_056_546_413_051));
+        assert!(!is_prime(u64::MAX));
+    }
+
+    #[test]
+    fn test_factorise() {
+        assert_eq!(factorise(1), vec![]);
+        assert_eq!(factorise(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(
+            factorise(u64::MAX),
+            vec![
+                (3, 1),
+                (5, 1),
+                (17, 1),
+                (257, 1),
+                (641, 1),
+                (65537, 1),
+                (6_700_417, 1)
+            ]
+        );
+        // a product of two large primes
+        let (p, q) = (4_294_967_291, 4_294_967_279);
+        assert_eq!(factorise(p * q), vec![(q, 1), (p, 1)]);
+        for n in 1..2000 {
+            let factors = factorise(n);
+            assert_eq!(factors.iter().map(|&(p, k)| p.pow(k)).product::<u64>(), n);
+            assert!(factors.iter().all(|&(p, _)| is_prime(p)));
+        }
+    }
+
+    #[test]
+    fn test_totient_and_order() {
+        for m in 1..300 {
+            let coprime = (1..=m).filter(|&a| gcd(a, m) == 1).collect::<Vec<_>>();
+            assert_eq!(totient(m), coprime.len() as u64);
+            for a in 0..m {
+                let expected = if gcd(a, m) == 1 {
+                    (1..=m).find(|&k| mod_pow(a, k, m) == 1 % m)
+                } else {
+                    None
+                };
+                assert_eq!(multiplicative_order(a, m), expected, "{} mod {}", a, m);
+            }
+        }
+        assert_eq!(multiplicative_order(7, 20201227), Some(20201226));
+    }
+
+    #[test]
+    fn test_primitive_root() {
+        for m in 1..300 {
+            let phi = totient(m);
+            let expected = (0..m).find(|&g| multiplicative_order(g, m) == Some(phi));
+            assert_eq!(primitive_root(m), expected, "mod {}", m);
+        }
+        assert_eq!(primitive_root(20201227), Some(7));
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd_of(&[12, 18, 30]), 6);
+        assert_eq!(gcd_of(&[]), 0);
+        assert_eq!(lcm_of(&[4, 6, 10]), Some(60));
+        assert_eq!(lcm_of(&[]), Some(1));
+        assert_eq!(lcm_of(&[7, 13, 59, 31, 19]), Some(3162341));
+        assert_eq!(lcm_of(&[u64::MAX, u64::MAX - 1]), None);
+        for a in 0..50 {
+            for b in 0..50 {
+                let expected = (1..=a * b).find(|l| l % a == 0 && l % b == 0).unwrap_or(0);
+                assert_eq!(lcm(a, b), Some(expected));
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::coor::Coor;
+use crate::grid::Grid;
+use crate::symmetry::D4;
+use anyhow::{Error, Result};
+use std::convert::TryFrom;
+
+// a rectangular pattern where `None` cells match anything
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern<T> {
+    cells: Grid<Option<T>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Match {
+    // top left corner of the transformed pattern
+    pub position: Coor,
+    pub orientation: D4,
+}
+
+impl<T> Pattern<T>
+where
+    T: TryFrom<char>,
+    <T as TryFrom<char>>::Error: Into<Error>,
+{
+    // `wildcard` marks the "don't care" cells. lines shorter than the longest one are padded
+    // with wildcards, so trailing whitespace may be left out
+    pub fn parse(input: &str, wildcard: char) -> Result<Self> {
+        let width = input.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let mut cells = vec![];
+        for line in input.lines() {
+            let mut chars = line.chars();
+            for _ in 0..width {
+                cells.push(match chars.next() {
+                    Some(c) if c != wildcard => Some(T::try_from(c).map_err(|e| e.into())?),
+                    _ => None,
+                });
+            }
+        }
+        Ok(Pattern {
+            cells: Grid::from_cells(width, cells)?,
+        })
+    }
+}
+
+impl<T: Clone + PartialEq> Pattern<T> {
+    pub fn from_grid(cells: Grid<Option<T>>) -> Self {
+        Pattern { cells }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.height()
+    }
+
+    pub fn transformed(&self, orientation: D4) -> Self {
+        Pattern {
+            cells: self.cells.transformed(orientation),
+        }
+    }
+
+    pub fn matches_at(&self, grid: &Grid<T>, position: Coor) -> bool {
+        self.cells.iter().all(|(offset, expected)| match expected {
+            Some(value) => grid.get(&(position + offset)) == Some(value),
+            None => grid.contains(&(position + offset)),
+        })
+    }
+
+    fn find_oriented(&self, grid: &Grid<T>, orientation: D4) -> Vec<Match> {
+        if self.width() > grid.width() || self.height() > grid.height() {
+            return vec![];
+        }
+        let mut res = vec![];
+        for y in 0..=(grid.height() - self.height()) {
+            for x in 0..=(grid.width() - self.width()) {
+                let position = Coor::new(x as i64, y as i64);
+                if self.matches_at(grid, position) {
+                    res.push(Match {
+                        position,
+                        orientation,
+                    });
+                }
+            }
+        }
+        res
+    }
+
+    // all matches, including overlapping ones, of the pattern as given
+    pub fn find(&self, grid: &Grid<T>) -> Vec<Match> {
+        self.find_oriented(grid, D4::IDENTITY)
+    }
+
+    // all matches under any of the 8 orientations. orientations mapping the pattern onto
+    // itself are only searched once, so each set of matched cells is reported once
+    pub fn find_all_orientations(&self, grid: &Grid<T>) -> Vec<Match> {
+        let mut seen: Vec<Pattern<T>> = vec![];
+        let mut res = vec![];
+        for orientation in D4::all() {
+            let oriented = self.transformed(orientation);
+            if seen.contains(&oriented) {
+                continue;
+            }
+            res.extend(oriented.find_oriented(grid, orientation));
+            seen.push(oriented);
+        }
+        res
+    }
+
+    // the grid positions covered by the non-wildcard cells of a match
+    pub fn covered(&self, found: &Match) -> Vec<Coor> {
+        self.transformed(found.orientation)
+            .cells
+            .iter()
+            .filter(|(_, expected)| expected.is_some())
+            .map(|(offset, _)| found.position + offset)
+            .collect()
+    }
+
+    // overwrite the non-wildcard cells of every match with `value`
+    pub fn mask(&self, grid: &mut Grid<T>, matches: &[Match], value: T) {
+        for found in matches {
+            for coor in self.covered(found) {
+                if let Some(cell) = grid.get_mut(&coor) {
+                    *cell = value.clone();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "#.#.
+.#..
+#.#.
+...#";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let pattern: Pattern<char> = Pattern::parse("#\n ##", ' ')?;
+        assert_eq!(pattern.width(), 3);
+        assert_eq!(pattern.height(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_overlapping() -> Result<()> {
+        let grid: Grid<char> = Grid::parse("####\n####")?;
+        let pattern: Pattern<char> = Pattern::parse("##", ' ')?;
+        assert_eq!(pattern.find(&grid).len(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_all_orientations() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(GRID)?;
+        // an X only has one distinct orientation
+        let cross: Pattern<char> = Pattern::parse("# #\n # \n# #", ' ')?;
+        let found = cross.find_all_orientations(&grid);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].position, Coor::new(0, 0));
+
+        // a diagonal pair only has two
+        let diagonal: Pattern<char> = Pattern::parse("#\n #", ' ')?;
+        let found = diagonal.find_all_orientations(&grid);
+        assert_eq!(found.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mask() -> Result<()> {
+        let mut grid: Grid<char> = Grid::parse(GRID)?;
+        let pattern: Pattern<char> = Pattern::parse("#.\n.#", '.')?;
+        let found = pattern.find(&grid);
+        assert_eq!(found.len(), 3);
+        pattern.mask(&mut grid, &found, 'O');
+        assert_eq!(grid.to_string(), "O.#.\n.O..\n#.O.\n...O");
+        Ok(())
+    }
+}
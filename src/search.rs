@@ -0,0 +1,400 @@
+use crate::coor::Coor;
+use crate::grid::{Connectivity, Grid};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+// everything reached from the start node, with the cost to get there and the predecessor on
+// a cheapest path
+#[derive(Debug, Clone)]
+pub struct Reachable<N, C> {
+    start: N,
+    costs: HashMap<N, C>,
+    parents: HashMap<N, N>,
+}
+
+impl<N: Clone + Eq + Hash, C: Copy> Reachable<N, C> {
+    fn new(start: N, zero: C) -> Self {
+        let mut costs = HashMap::new();
+        costs.insert(start.clone(), zero);
+        Reachable {
+            start,
+            costs,
+            parents: HashMap::new(),
+        }
+    }
+
+    pub fn cost(&self, node: &N) -> Option<C> {
+        self.costs.get(node).copied()
+    }
+
+    pub fn contains(&self, node: &N) -> bool {
+        self.costs.contains_key(node)
+    }
+
+    // includes the start node
+    pub fn len(&self) -> usize {
+        self.costs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.costs.is_empty()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &N> {
+        self.costs.keys()
+    }
+
+    // from the start node to `goal`, both inclusive
+    pub fn path_to(&self, goal: &N) -> Option<Vec<N>> {
+        if !self.contains(goal) {
+            return None;
+        }
+        let mut path = vec![goal.clone()];
+        let mut node = goal;
+        while *node != self.start {
+            node = self.parents.get(node)?;
+            path.push(node.clone());
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+pub fn bfs<N, F, I>(start: N, mut successors: F) -> Reachable<N, usize>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut reachable = Reachable::new(start.clone(), 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        let distance = reachable.costs[&node];
+        for next in successors(&node) {
+            if reachable.contains(&next) {
+                continue;
+            }
+            reachable.costs.insert(next.clone(), distance + 1);
+            reachable.parents.insert(next.clone(), node.clone());
+            queue.push_back(next);
+        }
+    }
+    reachable
+}
+
+// shortest path (in number of steps) to the first node satisfying `is_goal`
+pub fn bfs_path<N, F, I, G>(start: N, mut successors: F, mut is_goal: G) -> Option<Vec<N>>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+    G: FnMut(&N) -> bool,
+{
+    let mut reachable = Reachable::new(start.clone(), 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        if is_goal(&node) {
+            return reachable.path_to(&node);
+        }
+        let distance = reachable.costs[&node];
+        for next in successors(&node) {
+            if reachable.contains(&next) {
+                continue;
+            }
+            reachable.costs.insert(next.clone(), distance + 1);
+            reachable.parents.insert(next.clone(), node.clone());
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+// some path (not necessarily the shortest) to a node satisfying `is_goal`, exploring depth
+// first. useful when all goals are equally deep, e.g. when filling in a puzzle
+pub fn dfs<N, F, I, G>(start: N, mut successors: F, mut is_goal: G) -> Option<Vec<N>>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+    G: FnMut(&N) -> bool,
+{
+    let mut seen = HashSet::new();
+    // each entry is a node along with the successors still to explore
+    let mut stack = vec![(start.clone(), successors(&start).into_iter())];
+    seen.insert(start.clone());
+    if is_goal(&start) {
+        return Some(vec![start]);
+    }
+    while let Some((_, pending)) = stack.last_mut() {
+        match pending.next() {
+            Some(next) => {
+                if !seen.insert(next.clone()) {
+                    continue;
+                }
+                if is_goal(&next) {
+                    let mut path = stack.into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+                    path.push(next);
+                    return Some(path);
+                }
+                let next_successors = successors(&next).into_iter();
+                stack.push((next, next_successors));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    None
+}
+
+pub fn dijkstra<N, C, F, I>(start: N, successors: F) -> Reachable<N, C>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+{
+    best_first(start, successors, |_| C::default(), |_| false).0
+}
+
+pub fn dijkstra_path<N, C, F, I, G>(start: N, successors: F, is_goal: G) -> Option<(Vec<N>, C)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    G: FnMut(&N) -> bool,
+{
+    astar(start, successors, |_| C::default(), is_goal)
+}
+
+// `heuristic` must never overestimate the remaining cost for the path to be the cheapest. it
+// needn't be consistent, since a node is expanded again whenever a cheaper way to it turns up
+pub fn astar<N, C, F, I, H, G>(
+    start: N,
+    successors: F,
+    heuristic: H,
+    is_goal: G,
+) -> Option<(Vec<N>, C)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    H: FnMut(&N) -> C,
+    G: FnMut(&N) -> bool,
+{
+    let (reachable, goal) = best_first(start, successors, heuristic, is_goal);
+    let goal = goal?;
+    let cost = reachable.cost(&goal)?;
+    Some((reachable.path_to(&goal)?, cost))
+}
+
+// min-heap entry ordered on priority alone, so nodes needn't be comparable
+struct Entry<N, C> {
+    priority: C,
+    cost: C,
+    node: N,
+}
+
+impl<N, C: Ord> PartialEq for Entry<N, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<N, C: Ord> Eq for Entry<N, C> {}
+
+impl<N, C: Ord> PartialOrd for Entry<N, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N, C: Ord> Ord for Entry<N, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+fn best_first<N, C, F, I, H, G>(
+    start: N,
+    mut successors: F,
+    mut heuristic: H,
+    mut is_goal: G,
+) -> (Reachable<N, C>, Option<N>)
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    H: FnMut(&N) -> C,
+    G: FnMut(&N) -> bool,
+{
+    let mut reachable = Reachable::new(start.clone(), C::default());
+    let mut heap = BinaryHeap::new();
+    heap.push(Entry {
+        priority: heuristic(&start),
+        cost: C::default(),
+        node: start,
+    });
+    while let Some(Entry { cost, node, .. }) = heap.pop() {
+        // stale entries, for nodes since reached more cheaply
+        if reachable.cost(&node).is_some_and(|known| known < cost) {
+            continue;
+        }
+        if is_goal(&node) {
+            return (reachable, Some(node));
+        }
+        for (next, step) in successors(&node) {
+            let next_cost = cost + step;
+            if let Some(known) = reachable.cost(&next) {
+                if known <= next_cost {
+                    continue;
+                }
+            }
+            reachable.costs.insert(next.clone(), next_cost);
+            reachable.parents.insert(next.clone(), node.clone());
+            heap.push(Entry {
+                priority: next_cost + heuristic(&next),
+                cost: next_cost,
+                node: next,
+            });
+        }
+    }
+    (reachable, None)
+}
+
+// successor function for walking a grid, only entering cells for which `passable` holds
+pub fn grid_successors<'a, T, P>(
+    grid: &'a Grid<T>,
+    connectivity: Connectivity,
+    passable: P,
+) -> impl Fn(&Coor) -> Vec<Coor> + 'a
+where
+    P: Fn(&T) -> bool + 'a,
+{
+    move |coor| {
+        grid.neighbours(coor, connectivity)
+            .filter(|n| passable(&grid[*n]))
+            .collect()
+    }
+}
+
+pub fn manhattan(a: &Coor, b: &Coor) -> u64 {
+    ((a.x - b.x).abs() + (a.y - b.y).abs()) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const MAZE: &str = "..#....
+.##.##.
+...#...
+.#...#.";
+
+    #[test]
+    fn test_bfs() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(MAZE)?;
+        let successors = grid_successors(&grid, Connectivity::Four, |&c| c == '.');
+        let reachable = bfs(Coor::new(0, 0), &successors);
+        let goal = Coor::new(6, 0);
+        assert_eq!(reachable.cost(&goal), Some(12));
+        let path = reachable.path_to(&goal).expect("reachable");
+        assert_eq!(path.len(), 13);
+        assert_eq!(path[0], Coor::new(0, 0));
+        assert!(path.windows(2).all(|w| manhattan(&w[0], &w[1]) == 1));
+        assert!(!reachable.contains(&Coor::new(2, 0)));
+
+        let path = bfs_path(Coor::new(0, 0), &successors, |c| *c == goal);
+        assert_eq!(path.map(|p| p.len()), Some(13));
+        assert_eq!(bfs_path(Coor::new(0, 0), &successors, |c| c.x == 7), None);
+
+        let diagonal = grid_successors(&grid, Connectivity::Eight, |&c| c == '.');
+        assert_eq!(bfs(Coor::new(0, 0), &diagonal).cost(&goal), Some(7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dfs() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(MAZE)?;
+        let successors = grid_successors(&grid, Connectivity::Four, |&c| c == '.');
+        let goal = Coor::new(6, 3);
+        let path = dfs(Coor::new(0, 0), &successors, |c| *c == goal).expect("reachable");
+        assert_eq!(path.first(), Some(&Coor::new(0, 0)));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|w| manhattan(&w[0], &w[1]) == 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dijkstra_and_astar() {
+        // a->b->c is cheaper than a->c directly
+        let edges: HashMap<char, Vec<(char, u64)>> = [
+            ('a', vec![('b', 1), ('c', 5)]),
+            ('b', vec![('c', 1), ('d', 7)]),
+            ('c', vec![('d', 2)]),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let successors = |n: &char| edges.get(n).cloned().unwrap_or_default();
+        let reachable = dijkstra('a', successors);
+        assert_eq!(reachable.cost(&'c'), Some(2));
+        assert_eq!(reachable.cost(&'d'), Some(4));
+        assert_eq!(reachable.path_to(&'d'), Some(vec!['a', 'b', 'c', 'd']));
+
+        assert_eq!(
+            dijkstra_path('a', successors, |&n| n == 'd'),
+            Some((vec!['a', 'b', 'c', 'd'], 4))
+        );
+        assert_eq!(dijkstra_path('a', successors, |&n| n == 'e'), None);
+    }
+
+    #[test]
+    fn test_astar_inconsistent_heuristic() {
+        // h(a) = 3 never overestimates, but is more than a->b plus h(b), so b is first reached
+        // the expensive way and has to be expanded again once the cheap way turns up
+        let edges: HashMap<char, Vec<(char, u64)>> = [
+            ('s', vec![('a', 1), ('b', 3)]),
+            ('a', vec![('b', 1)]),
+            ('b', vec![('g', 3)]),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let heuristic = |&n: &char| if n == 'a' { 3 } else { 0 };
+        assert_eq!(
+            astar(
+                's',
+                |n| edges.get(n).cloned().unwrap_or_default(),
+                heuristic,
+                |&n| n == 'g'
+            ),
+            Some((vec!['s', 'a', 'b', 'g'], 5))
+        );
+    }
+
+    #[test]
+    fn test_astar_matches_bfs() -> Result<()> {
+        let grid: Grid<char> = Grid::parse(MAZE)?;
+        let successors = grid_successors(&grid, Connectivity::Four, |&c| c == '.');
+        let goal = Coor::new(6, 0);
+        let (path, cost) = astar(
+            Coor::new(0, 0),
+            |c| successors(c).into_iter().map(|n| (n, 1)),
+            |c| manhattan(c, &goal),
+            |c| *c == goal,
+        )
+        .expect("reachable");
+        assert_eq!(cost, 12);
+        assert_eq!(path.len(), 13);
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
-use anyhow::{bail, Result};
+use crate::dot::Graph;
+use anyhow::{anyhow, bail, Result};
 use clap::{App, Arg};
-use std::fmt::Display;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
 use std::fs::File;
 use std::io::{self, Read};
+use std::str::FromStr;
 
 enum Part {
     Part1,
@@ -17,6 +20,82 @@ enum Source {
 struct Args {
     part: Part,
     source: Source,
+    options: Options,
+}
+
+// a part's answer, or the graph it's worked out on when `--dot` is given, or some other
+// text asked for in its place, like a listing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer<T> {
+    Value(T),
+    Graph(Graph),
+    Text(String),
+}
+
+impl<T: PartialEq> PartialEq<T> for Answer<T> {
+    fn eq(&self, other: &T) -> bool {
+        match self {
+            Answer::Value(value) => value == other,
+            Answer::Graph(_) | Answer::Text(_) => false,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Answer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Value(value) => write!(f, "{}", value),
+            Answer::Graph(graph) => write!(f, "{}", graph),
+            Answer::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+// an extra command line option understood by a day, e.g. `--backend dense`
+pub struct Opt {
+    pub name: &'static str,
+    pub help: &'static str,
+    // the placeholder for the option's value, or `None` for a plain flag
+    pub value: Option<&'static str>,
+}
+
+// the extra options given on the command line
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    given: HashMap<String, Option<String>>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    // for passing options in tests
+    pub fn with(mut self, name: &str, value: Option<&str>) -> Self {
+        self.given.insert(name.into(), value.map(|v| v.into()));
+        self
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.given.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.given.get(name).and_then(|v| v.as_deref())
+    }
+
+    pub fn parse<T>(&self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value(name)
+            .map(|v| {
+                v.parse()
+                    .map_err(|e| anyhow!("invalid value for `--{}`: {}", name, e))
+            })
+            .transpose()
+    }
 }
 
 fn read_stdin() -> Result<String> {
@@ -33,8 +112,8 @@ fn read_file(filename: &str) -> Result<String> {
     Ok(buffer)
 }
 
-fn parse_input() -> Result<Args> {
-    let matches = App::new("adventofcode")
+fn parse_input(opts: &[Opt]) -> Result<Args> {
+    let mut app = App::new("adventofcode")
         .arg(
             Arg::with_name("part")
                 .short("p")
@@ -47,8 +126,15 @@ fn parse_input() -> Result<Args> {
                 .help("Sets the input file to use, or `-` for stdin")
                 .required(true)
                 .index(1),
-        )
-        .get_matches();
+        );
+    for opt in opts {
+        let mut arg = Arg::with_name(opt.name).long(opt.name).help(opt.help);
+        if let Some(value) = opt.value {
+            arg = arg.takes_value(true).value_name(value);
+        }
+        app = app.arg(arg);
+    }
+    let matches = app.get_matches();
 
     let part = match matches.value_of("part").unwrap_or("1") {
         "1" => Part::Part1,
@@ -62,24 +148,37 @@ fn parse_input() -> Result<Args> {
         "-" => Source::Stdin,
         filename => Source::File(filename.into()),
     };
-    Ok(Args { part, source })
+    let mut options = Options::new();
+    for opt in opts {
+        if matches.is_present(opt.name) {
+            options = options.with(opt.name, matches.value_of(opt.name));
+        }
+    }
+    Ok(Args {
+        part,
+        source,
+        options,
+    })
 }
 
 type DayFunc<T> = fn(&str) -> Result<T>;
+type OptionsFunc<T> = fn(&str, &Options) -> Result<T>;
 
-fn run<S, T>(part1: &DayFunc<S>, part2: &DayFunc<T>) -> Result<String>
+fn run<S, T, F, G>(part1: F, part2: G, opts: &[Opt]) -> Result<String>
 where
     S: Display,
     T: Display,
+    F: Fn(&str, &Options) -> Result<S>,
+    G: Fn(&str, &Options) -> Result<T>,
 {
-    let args = parse_input()?;
+    let args = parse_input(opts)?;
     let input = match args.source {
         Source::Stdin => read_stdin(),
         Source::File(filename) => read_file(&filename),
     }?;
     match args.part {
-        Part::Part1 => part1(&input).map(|res| format!("{}", res)),
-        Part::Part2 => part2(&input).map(|res| format!("{}", res)),
+        Part::Part1 => part1(&input, &args.options).map(|res| format!("{}", res)),
+        Part::Part2 => part2(&input, &args.options).map(|res| format!("{}", res)),
     }
 }
 
@@ -88,7 +187,37 @@ where
     S: Display,
     T: Display,
 {
-    let result = run(&part1, &part2)?;
+    let result = run(|input, _| part1(input), |input, _| part2(input), &[])?;
     println!("{}", result);
     Ok(())
 }
+
+// for days taking extra command line options
+pub fn dispatch_with<S, T>(part1: OptionsFunc<S>, part2: OptionsFunc<T>, opts: &[Opt]) -> Result<()>
+where
+    S: Display,
+    T: Display,
+{
+    let result = run(part1, part2, opts)?;
+    println!("{}", result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer() {
+        let mut graph = Graph::undirected("tiles");
+        graph.add_edge(crate::dot::Edge::new(1, 2));
+        assert_eq!(
+            Answer::<usize>::Graph(graph.clone()).to_string(),
+            graph.to_string()
+        );
+        assert_eq!(Answer::Value(5), 5);
+        assert_ne!(Answer::Graph(graph), 5);
+        assert_eq!(Answer::<usize>::Text("5".into()).to_string(), "5");
+        assert_ne!(Answer::Text("5".into()), 5);
+    }
+}
@@ -0,0 +1,483 @@
+use crate::coor::{Coor, CoorN, Hex};
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::hash_map::{self, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
+pub trait Bounded: Copy + Eq + Hash {
+    fn component_min(&self, other: &Self) -> Self;
+    fn component_max(&self, other: &Self) -> Self;
+}
+
+impl Bounded for Coor {
+    fn component_min(&self, other: &Self) -> Self {
+        Coor::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    fn component_max(&self, other: &Self) -> Self {
+        Coor::new(self.x.max(other.x), self.y.max(other.y))
+    }
+}
+
+impl<const N: usize> Bounded for CoorN<N> {
+    fn component_min(&self, other: &Self) -> Self {
+        let mut res = self.0;
+        for (r, o) in res.iter_mut().zip(other.0.iter()) {
+            *r = (*r).min(*o);
+        }
+        CoorN(res)
+    }
+
+    fn component_max(&self, other: &Self) -> Self {
+        let mut res = self.0;
+        for (r, o) in res.iter_mut().zip(other.0.iter()) {
+            *r = (*r).max(*o);
+        }
+        CoorN(res)
+    }
+}
+
+impl Bounded for Hex {
+    fn component_min(&self, other: &Self) -> Self {
+        Hex::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    fn component_max(&self, other: &Self) -> Self {
+        Hex::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+}
+
+// inclusive on both ends
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BoundingBox<C> {
+    pub min: C,
+    pub max: C,
+}
+
+impl<C: Bounded> BoundingBox<C> {
+    pub fn new(min: C, max: C) -> Self {
+        BoundingBox { min, max }
+    }
+
+    pub fn point(coor: C) -> Self {
+        BoundingBox {
+            min: coor,
+            max: coor,
+        }
+    }
+
+    pub fn include(&mut self, coor: &C) {
+        self.min = self.min.component_min(coor);
+        self.max = self.max.component_max(coor);
+    }
+
+    pub fn contains(&self, coor: &C) -> bool {
+        self.min.component_min(coor) == self.min && self.max.component_max(coor) == self.max
+    }
+}
+
+// an unbounded grid only storing the cells that are set. the bounding box is kept up to date
+// on insert and recomputed lazily after removals
+#[derive(Debug, Clone)]
+pub struct SparseGrid<C: Bounded, T = ()> {
+    cells: HashMap<C, T>,
+    bounds: Cell<Option<BoundingBox<C>>>,
+    stale: Cell<bool>,
+}
+
+impl<C: Bounded, T> SparseGrid<C, T> {
+    pub fn new() -> Self {
+        SparseGrid {
+            cells: HashMap::new(),
+            bounds: Cell::new(None),
+            stale: Cell::new(false),
+        }
+    }
+
+    pub fn insert(&mut self, coor: C, value: T) -> Option<T> {
+        if !self.stale.get() {
+            let bounds = match self.bounds.get() {
+                Some(mut bounds) => {
+                    bounds.include(&coor);
+                    bounds
+                }
+                None => BoundingBox::point(coor),
+            };
+            self.bounds.set(Some(bounds));
+        }
+        self.cells.insert(coor, value)
+    }
+
+    pub fn remove(&mut self, coor: &C) -> Option<T> {
+        let removed = self.cells.remove(coor);
+        if removed.is_some() {
+            self.stale.set(true);
+        }
+        removed
+    }
+
+    pub fn get(&self, coor: &C) -> Option<&T> {
+        self.cells.get(coor)
+    }
+
+    pub fn get_mut(&mut self, coor: &C) -> Option<&mut T> {
+        self.cells.get_mut(coor)
+    }
+
+    pub fn contains(&self, coor: &C) -> bool {
+        self.cells.contains_key(coor)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn count<F>(&self, f: F) -> usize
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.cells.values().filter(|t| f(t)).count()
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, C, T> {
+        self.cells.iter()
+    }
+
+    pub fn coors(&self) -> hash_map::Keys<'_, C, T> {
+        self.cells.keys()
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox<C>> {
+        if self.stale.get() {
+            let mut coors = self.cells.keys();
+            let bounds = coors.next().map(|first| {
+                let mut bounds = BoundingBox::point(*first);
+                for coor in coors {
+                    bounds.include(coor);
+                }
+                bounds
+            });
+            self.bounds.set(bounds);
+            self.stale.set(false);
+        }
+        self.bounds.get()
+    }
+}
+
+impl<C: Bounded, T: Clone> SparseGrid<C, T> {
+    // values are taken from `self` where both grids have a cell
+    pub fn union(&self, other: &Self) -> Self {
+        let mut res = self.clone();
+        for (coor, value) in other.iter() {
+            if !res.contains(coor) {
+                res.insert(*coor, value.clone());
+            }
+        }
+        res
+    }
+
+    pub fn intersection<U>(&self, other: &SparseGrid<C, U>) -> Self {
+        self.filter(|coor, _| other.contains(coor))
+    }
+
+    pub fn difference<U>(&self, other: &SparseGrid<C, U>) -> Self {
+        self.filter(|coor, _| !other.contains(coor))
+    }
+
+    pub fn crop(&self, window: &BoundingBox<C>) -> Self {
+        self.filter(|coor, _| window.contains(coor))
+    }
+
+    pub fn filter<F>(&self, f: F) -> Self
+    where
+        F: Fn(&C, &T) -> bool,
+    {
+        self.iter()
+            .filter(|(coor, value)| f(coor, value))
+            .map(|(coor, value)| (*coor, value.clone()))
+            .collect()
+    }
+}
+
+impl<C: Bounded> SparseGrid<C, ()> {
+    pub fn insert_point(&mut self, coor: C) -> bool {
+        self.insert(coor, ()).is_none()
+    }
+}
+
+impl<C: Bounded, T> Default for SparseGrid<C, T> {
+    fn default() -> Self {
+        SparseGrid::new()
+    }
+}
+
+impl<C: Bounded, T: PartialEq> PartialEq for SparseGrid<C, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cells == other.cells
+    }
+}
+
+impl<C: Bounded, T: Eq> Eq for SparseGrid<C, T> {}
+
+// combines the cells' hashes in an order-independent way, as the iteration order of equal
+// grids can differ
+impl<C: Bounded, T: Hash> Hash for SparseGrid<C, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let combined = self
+            .iter()
+            .map(|cell| {
+                let mut hasher = DefaultHasher::new();
+                cell.hash(&mut hasher);
+                hasher.finish()
+            })
+            .fold(0u64, |acc, h| acc.wrapping_add(h));
+        state.write_usize(self.len());
+        state.write_u64(combined);
+    }
+}
+
+impl<C: Bounded, T> FromIterator<(C, T)> for SparseGrid<C, T> {
+    fn from_iter<I: IntoIterator<Item = (C, T)>>(iter: I) -> Self {
+        let mut grid = SparseGrid::new();
+        for (coor, value) in iter {
+            grid.insert(coor, value);
+        }
+        grid
+    }
+}
+
+impl<C: Bounded> FromIterator<C> for SparseGrid<C, ()> {
+    fn from_iter<I: IntoIterator<Item = C>>(iter: I) -> Self {
+        iter.into_iter().map(|coor| (coor, ())).collect()
+    }
+}
+
+impl<T> SparseGrid<Coor, T> {
+    pub fn render<F>(&self, f: F) -> String
+    where
+        F: Fn(Option<&T>) -> char,
+    {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+        (bounds.min.y..=bounds.max.y)
+            .map(|y| {
+                (bounds.min.x..=bounds.max.x)
+                    .map(|x| f(self.get(&Coor::new(x, y))))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn dimension_name(dimension: usize) -> String {
+    match dimension {
+        0 => "x".into(),
+        1 => "y".into(),
+        2 => "z".into(),
+        3 => "w".into(),
+        n => format!("d{}", n),
+    }
+}
+
+impl<T, const N: usize> SparseGrid<CoorN<N>, T> {
+    // one x/y slice per combination of the remaining coordinates, labelled like `z=0, w=1`
+    pub fn render<F>(&self, f: F) -> String
+    where
+        F: Fn(Option<&T>) -> char,
+    {
+        self.render_slices(f, true)
+    }
+
+    // as `render`, optionally without the labels, e.g. for images where they'd be drawn as
+    // extra rows of cells
+    pub fn render_slices<F>(&self, f: F, labelled: bool) -> String
+    where
+        F: Fn(Option<&T>) -> char,
+    {
+        let bounds = match self.bounds() {
+            Some(bounds) if N >= 2 => bounds,
+            _ => return String::new(),
+        };
+        let mut slices = vec![];
+        let mut rest = bounds.min;
+        loop {
+            let mut slice = String::new();
+            if N > 2 && labelled {
+                let label = (2..N)
+                    .map(|d| format!("{}={}", dimension_name(d), rest.0[d]))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                slice.push_str(&label);
+                slice.push('\n');
+            }
+            let rows = (bounds.min.0[1]..=bounds.max.0[1])
+                .map(|y| {
+                    (bounds.min.0[0]..=bounds.max.0[0])
+                        .map(|x| {
+                            let mut coor = rest;
+                            coor.0[0] = x;
+                            coor.0[1] = y;
+                            f(self.get(&coor))
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            slice.push_str(&rows);
+            slices.push(slice);
+
+            // odometer-style increment over the dimensions beyond x and y
+            let mut d = 2;
+            while d < N {
+                if rest.0[d] < bounds.max.0[d] {
+                    rest.0[d] += 1;
+                    break;
+                }
+                rest.0[d] = bounds.min.0[d];
+                d += 1;
+            }
+            if d >= N {
+                break;
+            }
+        }
+        slices.join("\n\n")
+    }
+}
+
+impl<T> SparseGrid<Hex, T> {
+    // rows of constant z, each shifted by half a cell so neighbours line up
+    pub fn render<F>(&self, f: F) -> String
+    where
+        F: Fn(Option<&T>) -> char,
+    {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+        (bounds.min.z..=bounds.max.z)
+            .map(|z| {
+                let mut row = " ".repeat((z - bounds.min.z) as usize);
+                let cells = (bounds.min.x..=bounds.max.x)
+                    .map(|x| f(self.get(&Hex::new(x, -x - z, z))).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                row.push_str(&cells);
+                row.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl<C: Bounded + fmt::Debug, T> fmt::Display for SparseGrid<C, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bounds() {
+            Some(bounds) => write!(
+                f,
+                "{} cells in {:?}..={:?}",
+                self.len(),
+                bounds.min,
+                bounds.max
+            ),
+            None => write!(f, "empty"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coors: &[(i64, i64)]) -> SparseGrid<Coor> {
+        coors.iter().map(|&(x, y)| Coor::new(x, y)).collect()
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut grid = points(&[(0, 0), (3, -2), (-1, 5)]);
+        assert_eq!(
+            grid.bounds(),
+            Some(BoundingBox::new(Coor::new(-1, -2), Coor::new(3, 5)))
+        );
+        grid.remove(&Coor::new(-1, 5));
+        assert_eq!(
+            grid.bounds(),
+            Some(BoundingBox::new(Coor::new(0, -2), Coor::new(3, 0)))
+        );
+        grid.insert_point(Coor::new(4, 4));
+        assert_eq!(
+            grid.bounds(),
+            Some(BoundingBox::new(Coor::new(0, -2), Coor::new(4, 4)))
+        );
+        grid.remove(&Coor::new(0, 0));
+        grid.remove(&Coor::new(3, -2));
+        grid.remove(&Coor::new(4, 4));
+        assert_eq!(grid.bounds(), None);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = points(&[(0, 0), (1, 0), (2, 0)]);
+        let b = points(&[(1, 0), (2, 0), (3, 0)]);
+        assert_eq!(a.union(&b).len(), 4);
+        assert_eq!(a.intersection(&b), points(&[(1, 0), (2, 0)]));
+        assert_eq!(a.difference(&b), points(&[(0, 0)]));
+    }
+
+    #[test]
+    fn test_crop_and_count() {
+        let mut grid: SparseGrid<Coor, usize> = SparseGrid::new();
+        for x in 0..10 {
+            grid.insert(Coor::new(x, x), x as usize);
+        }
+        let window = BoundingBox::new(Coor::new(2, 0), Coor::new(5, 4));
+        let cropped = grid.crop(&window);
+        assert_eq!(cropped.len(), 3);
+        assert_eq!(cropped.count(|&v| v % 2 == 0), 2);
+    }
+
+    #[test]
+    fn test_render() {
+        let grid = points(&[(0, 0), (2, 1)]);
+        assert_eq!(
+            grid.render(|c| if c.is_some() { '#' } else { '.' }),
+            "#..\n..#"
+        );
+
+        let grid: SparseGrid<CoorN<3>> = [CoorN::new([0, 0, 0]), CoorN::new([1, 0, 1])]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(
+            grid.render(|c| if c.is_some() { '#' } else { '.' }),
+            "z=0\n#.\n\nz=1\n.#"
+        );
+        assert_eq!(
+            grid.render_slices(|c| if c.is_some() { '#' } else { '.' }, false),
+            "#.\n\n.#"
+        );
+
+        let grid: SparseGrid<Hex> = Hex::default().neighbours().collect();
+        assert_eq!(
+            grid.render(|c| if c.is_some() { '#' } else { '.' }),
+            ". # #\n # . #\n  # # ."
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::coor::Coor;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+pub const EDGES: [Edge; 4] = [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left];
+
+impl Edge {
+    pub fn facing(&self) -> Edge {
+        use Edge::*;
+        match self {
+            Top => Bottom,
+            Bottom => Top,
+            Left => Right,
+            Right => Left,
+        }
+    }
+
+    // pointing outwards, with y growing downwards
+    pub fn normal(&self) -> Coor {
+        match self {
+            Edge::Top => Coor::new(0, -1),
+            Edge::Right => Coor::new(1, 0),
+            Edge::Bottom => Coor::new(0, 1),
+            Edge::Left => Coor::new(-1, 0),
+        }
+    }
+
+    fn from_normal(normal: Coor) -> Edge {
+        *EDGES
+            .iter()
+            .find(|e| e.normal() == normal)
+            .expect("normal of a unit edge")
+    }
+
+    // edges are read in the direction of increasing coordinates, i.e. top and bottom
+    // left to right, left and right top to bottom
+    pub fn reading_direction(&self) -> Coor {
+        match self {
+            Edge::Top | Edge::Bottom => Coor::new(1, 0),
+            Edge::Left | Edge::Right => Coor::new(0, 1),
+        }
+    }
+}
+
+// an element of the symmetry group of the square: an optional horizontal flip followed by
+// `rotation` clockwise quarter turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct D4 {
+    flipped: bool,
+    rotation: u8,
+}
+
+// (a, b, c, d) mapping (x, y) to (a x + b y, c x + d y)
+type Matrix = (i64, i64, i64, i64);
+
+fn mat_mul(m: Matrix, n: Matrix) -> Matrix {
+    (
+        m.0 * n.0 + m.1 * n.2,
+        m.0 * n.1 + m.1 * n.3,
+        m.2 * n.0 + m.3 * n.2,
+        m.2 * n.1 + m.3 * n.3,
+    )
+}
+
+impl D4 {
+    pub const IDENTITY: D4 = D4 {
+        flipped: false,
+        rotation: 0,
+    };
+
+    pub fn new(flipped: bool, rotation: u8) -> Self {
+        D4 {
+            flipped,
+            rotation: rotation % 4,
+        }
+    }
+
+    pub fn all() -> impl Iterator<Item = D4> {
+        [false, true]
+            .iter()
+            .flat_map(|&flipped| (0..4).map(move |rotation| D4::new(flipped, rotation)))
+    }
+
+    pub fn flipped(&self) -> bool {
+        self.flipped
+    }
+
+    pub fn rotation(&self) -> u8 {
+        self.rotation
+    }
+
+    fn matrix(&self) -> Matrix {
+        let mut m = if self.flipped {
+            (-1, 0, 0, 1)
+        } else {
+            (1, 0, 0, 1)
+        };
+        for _ in 0..self.rotation {
+            // clockwise on screen: (x, y) -> (-y, x)
+            m = mat_mul((0, -1, 1, 0), m);
+        }
+        m
+    }
+
+    fn from_matrix(m: Matrix) -> D4 {
+        D4::all()
+            .find(|d| d.matrix() == m)
+            .expect("matrix of a square symmetry")
+    }
+
+    // apply `self` first, then `other`
+    pub fn then(&self, other: D4) -> D4 {
+        D4::from_matrix(mat_mul(other.matrix(), self.matrix()))
+    }
+
+    pub fn inverse(&self) -> D4 {
+        D4::all()
+            .find(|d| self.then(*d) == D4::IDENTITY)
+            .expect("every element has an inverse")
+    }
+
+    pub fn apply(&self, coor: Coor) -> Coor {
+        let m = self.matrix();
+        Coor::new(m.0 * coor.x + m.1 * coor.y, m.2 * coor.x + m.3 * coor.y)
+    }
+
+    // size of a `width` x `height` box after the transformation
+    pub fn dimensions(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.rotation.is_multiple_of(2) {
+            (width, height)
+        } else {
+            (height, width)
+        }
+    }
+
+    // maps a position within a `width` x `height` box to the corresponding position in the
+    // transformed box, keeping (0, 0) as the top left corner
+    pub fn apply_in_box(&self, coor: Coor, width: usize, height: usize) -> Coor {
+        let a = self.apply(Coor::new(0, 0));
+        let b = self.apply(Coor::new(width as i64 - 1, height as i64 - 1));
+        let offset = Coor::new(a.x.min(b.x), a.y.min(b.y));
+        let p = self.apply(coor);
+        Coor::new(p.x - offset.x, p.y - offset.y)
+    }
+
+    // where `edge` ends up, and whether it now reads backwards
+    pub fn apply_edge(&self, edge: Edge) -> (Edge, bool) {
+        let moved = Edge::from_normal(self.apply(edge.normal()));
+        let direction = self.apply(edge.reading_direction());
+        (moved, direction != moved.reading_direction())
+    }
+}
+
+impl Default for D4 {
+    fn default() -> Self {
+        D4::IDENTITY
+    }
+}
+
+impl fmt::Display for D4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.flipped {
+            write!(f, "flip, ")?;
+        }
+        write!(f, "rotate {}°", self.rotation as usize * 90)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use anyhow::Result;
+
+    #[test]
+    fn test_group() {
+        assert_eq!(D4::all().count(), 8);
+        for a in D4::all() {
+            assert_eq!(a.then(D4::IDENTITY), a);
+            assert_eq!(D4::IDENTITY.then(a), a);
+            assert_eq!(a.then(a.inverse()), D4::IDENTITY);
+            for b in D4::all() {
+                for c in D4::all() {
+                    assert_eq!(a.then(b).then(c), a.then(b.then(c)));
+                }
+            }
+        }
+        let quarter = D4::new(false, 1);
+        assert_eq!(
+            quarter.then(quarter).then(quarter).then(quarter),
+            D4::IDENTITY
+        );
+        let flip = D4::new(true, 0);
+        assert_eq!(flip.then(flip), D4::IDENTITY);
+    }
+
+    #[test]
+    fn test_grid() -> Result<()> {
+        let grid: Grid<char> = Grid::parse("ab\ncd\nef")?;
+        assert_eq!(grid.transformed(D4::new(false, 1)).to_string(), "eca\nfdb");
+        assert_eq!(grid.transformed(D4::new(true, 0)).to_string(), "ba\ndc\nfe");
+        assert_eq!(grid.transformed(D4::new(true, 1)).to_string(), "fdb\neca");
+        for a in D4::all() {
+            for b in D4::all() {
+                assert_eq!(
+                    grid.transformed(a).transformed(b),
+                    grid.transformed(a.then(b))
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_box() {
+        for d in D4::all() {
+            let (w, h) = d.dimensions(3, 2);
+            for x in 0..3 {
+                for y in 0..2 {
+                    let p = d.apply_in_box(Coor::new(x, y), 3, 2);
+                    assert!(p.x >= 0 && p.y >= 0 && p.x < w as i64 && p.y < h as i64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edges() -> Result<()> {
+        let grid: Grid<char> = Grid::parse("abc\ndef\nghi")?;
+        for d in D4::all() {
+            let transformed = grid.transformed(d);
+            for &edge in &EDGES {
+                let (moved, reversed) = d.apply_edge(edge);
+                let mut expected = grid.edge(edge);
+                if reversed {
+                    expected.reverse();
+                }
+                assert_eq!(transformed.edge(moved), expected);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use crate::shared::{Opt, Options};
+use anyhow::{anyhow, bail, Error, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+// for days that can show their simulation
+pub const VISUALISE: Opt = Opt {
+    name: "visualise",
+    help: "Show every generation: `text:DIR`, `ppm:DIR`, `pgm:DIR` or `terminal[:MILLIS]`",
+    value: Some("TARGET"),
+};
+
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+// pixels per character in images
+const DEFAULT_SCALE: usize = 4;
+
+// where frames go
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Target {
+    // one `.txt` file per generation
+    Text(PathBuf),
+    // one colour image per generation
+    Ppm(PathBuf),
+    // one greyscale image per generation
+    Pgm(PathBuf),
+    // redrawn in place on stderr, pausing between generations
+    Terminal(Duration),
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().expect("split always yields a part");
+        let arg = parts.next();
+        let dir = || -> Result<PathBuf> {
+            match arg {
+                Some(dir) if !dir.is_empty() => Ok(dir.into()),
+                _ => bail!("`{}` needs a directory, e.g. `{}:frames`", kind, kind),
+            }
+        };
+        Ok(match kind {
+            "text" => Target::Text(dir()?),
+            "ppm" => Target::Ppm(dir()?),
+            "pgm" => Target::Pgm(dir()?),
+            "terminal" => Target::Terminal(match arg {
+                Some(millis) => Duration::from_millis(
+                    millis
+                        .parse()
+                        .map_err(|e| anyhow!("invalid delay `{}`: {}", millis, e))?,
+                ),
+                None => DEFAULT_DELAY,
+            }),
+            _ => bail!("unknown visualisation `{}`", kind),
+        })
+    }
+}
+
+// the colour of each character in images. anything else is drawn in the background colour
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colours: HashMap<char, [u8; 3]>,
+    background: [u8; 3],
+}
+
+impl Palette {
+    pub fn new(colours: &[(char, [u8; 3])]) -> Self {
+        Palette {
+            colours: colours.iter().cloned().collect(),
+            background: [0, 0, 0],
+        }
+    }
+
+    pub fn colour(&self, c: char) -> [u8; 3] {
+        self.colours.get(&c).copied().unwrap_or(self.background)
+    }
+
+    pub fn grey(&self, c: char) -> u8 {
+        luma(self.colour(c))
+    }
+}
+
+fn luma([r, g, b]: [u8; 3]) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+}
+
+// lines of text padded to a rectangle, one pixel per character
+fn raster(text: &str) -> (usize, usize, Vec<Vec<char>>) {
+    let lines = text
+        .lines()
+        .map(|l| l.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+    (width, lines.len(), lines)
+}
+
+fn image<F>(text: &str, scale: usize, magic: &str, pixel: F) -> Vec<u8>
+where
+    F: Fn(Option<char>) -> Vec<u8>,
+{
+    let (width, height, lines) = raster(text);
+    let mut res = format!("{}\n{} {}\n255\n", magic, width * scale, height * scale).into_bytes();
+    for line in lines {
+        let row = (0..width)
+            .flat_map(|x| {
+                let p = pixel(line.get(x).copied());
+                (0..scale).flat_map(move |_| p.clone())
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..scale {
+            res.extend(&row);
+        }
+    }
+    res
+}
+
+// a binary colour image, `scale` pixels square per character
+pub fn ppm(text: &str, palette: &Palette, scale: usize) -> Vec<u8> {
+    image(text, scale, "P6", |c| match c {
+        Some(c) => palette.colour(c).to_vec(),
+        None => palette.background.to_vec(),
+    })
+}
+
+// a binary greyscale image, `scale` pixels square per character
+pub fn pgm(text: &str, palette: &Palette, scale: usize) -> Vec<u8> {
+    image(text, scale, "P5", |c| match c {
+        Some(c) => vec![palette.grey(c)],
+        None => vec![luma(palette.background)],
+    })
+}
+
+pub struct Visualiser {
+    target: Target,
+    palette: Palette,
+    scale: usize,
+    frames: usize,
+}
+
+impl Visualiser {
+    pub fn new(target: Target, palette: Palette) -> Result<Self> {
+        match &target {
+            Target::Text(dir) | Target::Ppm(dir) | Target::Pgm(dir) => fs::create_dir_all(dir)?,
+            Target::Terminal(_) => {}
+        }
+        Ok(Visualiser {
+            target,
+            palette,
+            scale: DEFAULT_SCALE,
+            frames: 0,
+        })
+    }
+
+    // `None` unless `--visualise` was given
+    pub fn from_options(options: &Options, palette: Palette) -> Result<Option<Self>> {
+        options
+            .parse(VISUALISE.name)?
+            .map(|target| Visualiser::new(target, palette))
+            .transpose()
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    // whether frames are shown as text, so can carry labels. in images every character is a
+    // pixel
+    pub fn shows_text(&self) -> bool {
+        matches!(self.target, Target::Text(_) | Target::Terminal(_))
+    }
+
+    fn path(dir: &Path, frame: usize, extension: &str) -> PathBuf {
+        dir.join(format!("frame{:04}.{}", frame, extension))
+    }
+
+    pub fn frame(&mut self, text: &str) -> Result<()> {
+        let frame = self.frames;
+        match &self.target {
+            Target::Text(dir) => fs::write(Visualiser::path(dir, frame, "txt"), text)?,
+            Target::Ppm(dir) => fs::write(
+                Visualiser::path(dir, frame, "ppm"),
+                ppm(text, &self.palette, self.scale),
+            )?,
+            Target::Pgm(dir) => fs::write(
+                Visualiser::path(dir, frame, "pgm"),
+                pgm(text, &self.palette, self.scale),
+            )?,
+            Target::Terminal(delay) => {
+                let mut stderr = io::stderr();
+                // clear the screen and go back to the top left
+                write!(stderr, "\x1b[2J\x1b[H{}\n\ngeneration {}\n", text, frame)?;
+                stderr.flush()?;
+                thread::sleep(*delay);
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() -> Result<()> {
+        assert_eq!("ppm:out".parse::<Target>()?, Target::Ppm("out".into()));
+        assert_eq!(
+            "terminal".parse::<Target>()?,
+            Target::Terminal(DEFAULT_DELAY)
+        );
+        assert_eq!(
+            "terminal:250".parse::<Target>()?,
+            Target::Terminal(Duration::from_millis(250))
+        );
+        assert!("text".parse::<Target>().is_err());
+        assert!("terminal:soon".parse::<Target>().is_err());
+        assert!("gif:out".parse::<Target>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_images() {
+        let palette = Palette::new(&[('#', [255, 0, 0]), ('.', [0, 0, 255])]);
+        // the short second line is padded with the background
+        let image = ppm("#.\n#", &palette, 1);
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        assert_eq!(
+            &image[header.len()..],
+            &[255, 0, 0, 0, 0, 255, 255, 0, 0, 0, 0, 0]
+        );
+
+        let image = pgm("#.", &palette, 2);
+        let header = b"P5\n4 2\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        assert_eq!(&image[header.len()..], &[76, 76, 29, 29, 76, 76, 29, 29]);
+    }
+
+    #[test]
+    fn test_text_frames() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("aoc2020-visualise-{}", std::process::id()));
+        let mut visualiser = Visualiser::new(Target::Text(dir.clone()), Palette::new(&[]))?;
+        visualiser.frame("#.")?;
+        visualiser.frame(".#")?;
+        assert_eq!(visualiser.frames(), 2);
+        assert!(visualiser.shows_text());
+        assert_eq!(fs::read_to_string(dir.join("frame0001.txt"))?, ".#");
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
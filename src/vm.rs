@@ -0,0 +1,469 @@
+use anyhow::{anyhow, bail, Error, Result};
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Display};
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+// the handheld console's accumulator, the first register of every instruction set
+pub const ACC: usize = 0;
+
+// what a console can run. instructions are parsed one line at a time, and displayed the same way
+pub trait InstructionSet:
+    for<'a> TryFrom<&'a str, Error = Error> + Display + Debug + Clone
+{
+    // the names of the registers, in index order
+    const REGISTERS: &'static [&'static str];
+
+    // carries out the instruction, leaving the pc at the next one to run
+    fn execute(&self, state: &mut State);
+}
+
+// the boot code's instructions
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Instruction {
+    Acc(i64),
+    Jmp(i64),
+    Nop(i64),
+}
+
+impl TryFrom<&str> for Instruction {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(2, ' ');
+        let op = parts.next().expect("split always yields a part");
+        let arg = parts
+            .next()
+            .ok_or_else(|| anyhow!("missing argument in `{}`", s))?;
+        let n = arg
+            .parse()
+            .map_err(|_| anyhow!("invalid argument in `{}`", s))?;
+        Ok(match op {
+            "acc" => Instruction::Acc(n),
+            "jmp" => Instruction::Jmp(n),
+            "nop" => Instruction::Nop(n),
+            _ => bail!("Invalid instruction `{}`", s),
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Acc(n) => write!(f, "acc {:+}", n),
+            Instruction::Jmp(n) => write!(f, "jmp {:+}", n),
+            Instruction::Nop(n) => write!(f, "nop {:+}", n),
+        }
+    }
+}
+
+impl Instruction {
+    // where the pc goes after running this at `pc`
+    pub fn target(&self, pc: i64) -> i64 {
+        match self {
+            Instruction::Jmp(n) => pc + n,
+            Instruction::Acc(_) | Instruction::Nop(_) => pc + 1,
+        }
+    }
+}
+
+impl InstructionSet for Instruction {
+    const REGISTERS: &'static [&'static str] = &["acc"];
+
+    fn execute(&self, state: &mut State) {
+        match self {
+            Instruction::Acc(n) => {
+                state.registers[ACC] += n;
+                state.pc += 1;
+            }
+            Instruction::Jmp(n) => state.pc += n,
+            Instruction::Nop(_) => state.pc += 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Program<I> {
+    instructions: Vec<I>,
+}
+
+impl<I: InstructionSet> FromStr for Program<I> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let instructions = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| I::try_from(line).map_err(|e| anyhow!("line {}: {}", i + 1, e)))
+            .collect::<Result<_>>()?;
+        Ok(Program { instructions })
+    }
+}
+
+// one instruction per line, so it parses back to the same program
+impl<I: Display> fmt::Display for Program<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (pc, instruction) in self.instructions.iter().enumerate() {
+            if pc > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", instruction)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> From<Vec<I>> for Program<I> {
+    fn from(instructions: Vec<I>) -> Self {
+        Program { instructions }
+    }
+}
+
+impl<I> Program<I> {
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    // `None` outside the program, including for negative pcs
+    pub fn get(&self, pc: i64) -> Option<&I> {
+        usize::try_from(pc)
+            .ok()
+            .and_then(|pc| self.instructions.get(pc))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &I> {
+        self.instructions.iter()
+    }
+}
+
+impl<I> Index<usize> for Program<I> {
+    type Output = I;
+
+    fn index(&self, pc: usize) -> &I {
+        &self.instructions[pc]
+    }
+}
+
+// for patching instructions in place
+impl<I> IndexMut<usize> for Program<I> {
+    fn index_mut(&mut self, pc: usize) -> &mut I {
+        &mut self.instructions[pc]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct State {
+    pub pc: i64,
+    pub registers: Vec<i64>,
+}
+
+impl State {
+    pub fn new(registers: usize) -> Self {
+        State {
+            pc: 0,
+            registers: vec![0; registers],
+        }
+    }
+
+    pub fn acc(&self) -> i64 {
+        self.registers[ACC]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RunResult<I> {
+    // the pc reached the instruction just past the end
+    Terminated {
+        steps: usize,
+    },
+    // the instruction at `pc` was about to run a second time
+    Looped {
+        pc: usize,
+        steps: usize,
+    },
+    // the instruction at `pc` sent the pc to `target`, outside the program
+    OutOfBounds {
+        pc: usize,
+        instruction: I,
+        target: i64,
+        steps: usize,
+    },
+    // the step limit ran out first
+    StepLimit {
+        steps: usize,
+    },
+}
+
+pub struct Vm<'a, I> {
+    program: &'a Program<I>,
+    state: State,
+    steps: usize,
+    step_limit: Option<usize>,
+    // how often each instruction has run, for spotting loops
+    visits: Vec<usize>,
+    detect_loops: bool,
+    result: Option<RunResult<I>>,
+}
+
+impl<'a, I: InstructionSet> Vm<'a, I> {
+    pub fn new(program: &'a Program<I>) -> Self {
+        Vm {
+            program,
+            state: State::new(I::REGISTERS.len()),
+            steps: 0,
+            step_limit: None,
+            visits: vec![0; program.len()],
+            detect_loops: true,
+            result: None,
+        }
+    }
+
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    // on by default. only safe to turn off with a step limit, or for instruction sets where
+    // registers decide where jumps go, so revisiting a pc doesn't mean looping forever
+    pub fn with_loop_detection(mut self, detect_loops: bool) -> Self {
+        self.detect_loops = detect_loops;
+        self
+    }
+
+    pub fn program(&self) -> &Program<I> {
+        self.program
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    // the pc has to be left inside the program, or just past its end
+    pub fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // how many times the instruction at `pc` has run
+    pub fn visits(&self, pc: usize) -> usize {
+        self.visits.get(pc).copied().unwrap_or(0)
+    }
+
+    // a register by name, if the instruction set has it
+    pub fn register(&self, name: &str) -> Option<i64> {
+        let index = I::REGISTERS.iter().position(|&r| r == name)?;
+        Some(self.state.registers[index])
+    }
+
+    // the result, once the program has stopped
+    pub fn result(&self) -> Option<&RunResult<I>> {
+        self.result.as_ref()
+    }
+
+    fn check(&self) -> Option<RunResult<I>> {
+        let steps = self.steps;
+        if self.state.pc == self.program.len() as i64 {
+            return Some(RunResult::Terminated { steps });
+        }
+        let pc = self.state.pc as usize;
+        if self.detect_loops && self.visits[pc] > 0 {
+            return Some(RunResult::Looped { pc, steps });
+        }
+        if self.step_limit == Some(steps) {
+            return Some(RunResult::StepLimit { steps });
+        }
+        None
+    }
+
+    // runs one instruction, returning the result if that stopped the program. once stopped,
+    // just returns the result again
+    pub fn step(&mut self) -> Option<RunResult<I>> {
+        if self.result.is_none() {
+            self.result = self.check();
+        }
+        if let Some(result) = &self.result {
+            return Some(result.clone());
+        }
+        let pc = self.state.pc as usize;
+        let program = self.program;
+        let instruction = &program[pc];
+        instruction.execute(&mut self.state);
+        self.visits[pc] += 1;
+        self.steps += 1;
+        let target = self.state.pc;
+        if target < 0 || target > self.program.len() as i64 {
+            self.result = Some(RunResult::OutOfBounds {
+                pc,
+                instruction: instruction.clone(),
+                target,
+                steps: self.steps,
+            });
+        } else {
+            self.result = self.check();
+        }
+        self.result.clone()
+    }
+
+    pub fn run(&mut self) -> RunResult<I> {
+        loop {
+            if let Some(result) = self.step() {
+                return result;
+            }
+        }
+    }
+}
+
+// runs a program from the start, returning how it stopped and the state it stopped in
+pub fn run<I: InstructionSet>(program: &Program<I>) -> (RunResult<I>, State) {
+    let mut vm = Vm::new(program);
+    let result = vm.run();
+    (result, vm.state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let program: Program<Instruction> = INPUT.parse()?;
+        assert_eq!(program.len(), 9);
+        assert_eq!(program[2], Instruction::Jmp(4));
+        assert_eq!(program.get(-1), None);
+        let err = "nop +0\nmul 3".parse::<Program<Instruction>>().unwrap_err();
+        assert_eq!(err.to_string(), "line 2: Invalid instruction `mul 3`");
+        assert!("acc".parse::<Program<Instruction>>().is_err());
+        assert!("acc x".parse::<Program<Instruction>>().is_err());
+        assert_eq!(program.to_string(), INPUT);
+        // a trailing newline doesn't add an empty instruction
+        let with_newline = format!("{}\n", INPUT).parse::<Program<Instruction>>()?;
+        assert_eq!(with_newline, program);
+        assert_eq!(Instruction::Jmp(-4).to_string(), "jmp -4");
+        assert_eq!(Instruction::Jmp(-4).target(7), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_results() -> Result<()> {
+        let mut program: Program<Instruction> = INPUT.parse()?;
+        let (result, state) = run(&program);
+        assert_eq!(result, RunResult::Looped { pc: 1, steps: 7 });
+        assert_eq!(state.acc(), 5);
+
+        program[7] = Instruction::Nop(-4);
+        let (result, state) = run(&program);
+        assert_eq!(result, RunResult::Terminated { steps: 6 });
+        assert_eq!(state.acc(), 8);
+
+        program[0] = Instruction::Jmp(-2);
+        assert_eq!(
+            run(&program).0,
+            RunResult::OutOfBounds {
+                pc: 0,
+                instruction: Instruction::Jmp(-2),
+                target: -2,
+                steps: 1,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stepping() -> Result<()> {
+        let program: Program<Instruction> = INPUT.parse()?;
+        let mut vm = Vm::new(&program).with_step_limit(3);
+        assert_eq!(vm.step(), None);
+        assert_eq!(vm.step(), None);
+        assert_eq!(vm.state().pc, 2);
+        assert_eq!(vm.register("acc"), Some(1));
+        assert_eq!(vm.register("x"), None);
+        assert_eq!(vm.run(), RunResult::StepLimit { steps: 3 });
+        // stopped programs stay stopped
+        assert_eq!(vm.step(), Some(RunResult::StepLimit { steps: 3 }));
+        assert_eq!(vm.visits(1), 1);
+        Ok(())
+    }
+
+    // a console with a counter register, where revisiting a pc doesn't mean a loop
+    #[derive(Debug, PartialEq, Clone)]
+    enum Counter {
+        Acc(i64),
+        Dec,
+        // jumps while the counter is non-zero
+        Jnz(i64),
+    }
+
+    impl TryFrom<&str> for Counter {
+        type Error = Error;
+
+        fn try_from(s: &str) -> Result<Self> {
+            Ok(match s.split(' ').collect::<Vec<_>>().as_slice() {
+                ["acc", n] => Counter::Acc(n.parse()?),
+                ["dec"] => Counter::Dec,
+                ["jnz", n] => Counter::Jnz(n.parse()?),
+                _ => bail!("Invalid instruction `{}`", s),
+            })
+        }
+    }
+
+    impl fmt::Display for Counter {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Counter::Acc(n) => write!(f, "acc {:+}", n),
+                Counter::Dec => write!(f, "dec"),
+                Counter::Jnz(n) => write!(f, "jnz {:+}", n),
+            }
+        }
+    }
+
+    impl InstructionSet for Counter {
+        const REGISTERS: &'static [&'static str] = &["acc", "counter"];
+
+        fn execute(&self, state: &mut State) {
+            match self {
+                Counter::Acc(n) => state.registers[ACC] += n,
+                Counter::Dec => state.registers[1] -= 1,
+                Counter::Jnz(n) if state.registers[1] != 0 => {
+                    state.pc += n;
+                    return;
+                }
+                Counter::Jnz(_) => {}
+            }
+            state.pc += 1;
+        }
+    }
+
+    #[test]
+    fn test_instruction_set() -> Result<()> {
+        let program: Program<Counter> = "acc +2\ndec\njnz -2".parse()?;
+        assert_eq!(program.to_string(), "acc +2\ndec\njnz -2");
+        let mut vm = Vm::new(&program).with_loop_detection(false);
+        vm.state_mut().registers[1] = 5;
+        assert_eq!(vm.run(), RunResult::Terminated { steps: 15 });
+        assert_eq!(vm.register("acc"), Some(10));
+        assert_eq!(vm.register("counter"), Some(0));
+        assert_eq!(vm.visits(0), 5);
+
+        let mut vm = Vm::new(&program);
+        vm.state_mut().registers[1] = 5;
+        assert_eq!(vm.run(), RunResult::Looped { pc: 0, steps: 3 });
+        Ok(())
+    }
+}